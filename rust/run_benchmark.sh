@@ -1,2 +1,3 @@
 cargo build --release
 hyperfine --warmup 10 "./target/release/rust"
+hyperfine --warmup 10 -n sequential "./target/release/rust 11 --part 2" -n parallel "./target/release/rust 11 --part 2 --parallel"
@@ -2,12 +2,17 @@
 
 use std::{
     cmp::Ordering,
-    collections::HashSet,
+    collections::HashMap,
     fmt::{Debug, Display},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
     str::FromStr,
+    thread,
+    time::Duration,
 };
 
-use crate::utils::Day;
+use crate::utils::{has_flag, option_value, Day};
 
 #[derive(Debug)]
 struct ParseError;
@@ -36,6 +41,19 @@ impl FromStr for Motion {
     }
 }
 
+impl Display for Motion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let motion = match self {
+            Motion::Up => "U",
+            Motion::Down => "D",
+            Motion::Left => "L",
+            Motion::Right => "R",
+        };
+
+        write!(f, "{motion}")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct Instruction {
     motion: Motion,
@@ -49,6 +67,12 @@ impl Instruction {
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.motion, self.count)
+    }
+}
+
 impl FromStr for Instruction {
     type Err = ParseError;
 
@@ -66,6 +90,67 @@ impl FromStr for Instruction {
     }
 }
 
+/// When two knots are close enough that the back one doesn't have to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Adjacency {
+    /// The knots touch, also diagonally.
+    #[default]
+    Chebyshev,
+
+    /// The knots touch, but only horizontally or vertically.
+    Manhattan,
+}
+
+impl FromStr for Adjacency {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chebyshev" => Ok(Adjacency::Chebyshev),
+            "manhattan" => Ok(Adjacency::Manhattan),
+            _ => Err(ParseError),
+        }
+    }
+}
+
+/// How a knot follows the knot in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FollowRule {
+    adjacency: Adjacency,
+
+    /// If the knot can move diagonally, otherwise it moves along the axis with the larger distance.
+    diagonal_moves: bool,
+}
+
+impl Default for FollowRule {
+    fn default() -> Self {
+        Self {
+            adjacency: Adjacency::Chebyshev,
+            diagonal_moves: true,
+        }
+    }
+}
+
+/// The configuration of a rope, the puzzle uses 2 knots for part 1 and 10 for part 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RopeConfig {
+    knots: usize,
+    rule: FollowRule,
+
+    /// If the visits of every knot are tracked, otherwise only the visits of the tail.
+    track_every_knot: bool,
+}
+
+impl Default for RopeConfig {
+    fn default() -> Self {
+        Self {
+            knots: 2,
+            rule: FollowRule::default(),
+            track_every_knot: false,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
 struct Position {
     x: i32,
@@ -89,12 +174,18 @@ impl Position {
         self
     }
 
-    fn is_adjacent_to(&self, other: &Position) -> bool {
-        (self.x - other.x).abs() <= 1 && (self.y - other.y).abs() <= 1
+    fn is_adjacent_to(&self, other: &Position, adjacency: Adjacency) -> bool {
+        let x_distance = (self.x - other.x).abs();
+        let y_distance = (self.y - other.y).abs();
+
+        match adjacency {
+            Adjacency::Chebyshev => x_distance <= 1 && y_distance <= 1,
+            Adjacency::Manhattan => x_distance + y_distance <= 1,
+        }
     }
 
-    fn follow(&mut self, other: &Position) -> &mut Self {
-        if self.is_adjacent_to(other) {
+    fn follow(&mut self, other: &Position, rule: FollowRule) -> &mut Self {
+        if self.is_adjacent_to(other, rule.adjacency) {
             return self;
         }
 
@@ -110,8 +201,18 @@ impl Position {
             Ordering::Greater => -1,
         };
 
-        self.x += x_delta;
-        self.y += y_delta;
+        let x_distance = (self.x - other.x).abs();
+        let y_distance = (self.y - other.y).abs();
+
+        // A diagonal step from a diagonal neighbor would move onto the knot in front
+        if rule.diagonal_moves && (x_distance, y_distance) != (1, 1) {
+            self.x += x_delta;
+            self.y += y_delta;
+        } else if x_distance >= y_distance {
+            self.x += x_delta;
+        } else {
+            self.y += y_delta;
+        }
 
         self
     }
@@ -130,12 +231,56 @@ impl Debug for Position {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-struct Rope<const L: usize> {
-    knots: [Position; L],
-    visited_by_tail: HashSet<Position>,
+struct Rope {
+    rule: FollowRule,
+    knots: Vec<Position>,
+
+    /// How often every knot has moved onto each position, counting the start as the first visit.
+    ///
+    /// Only the tail is tracked, unless `track_every_knot` is set.
+    visits: Vec<HashMap<Position, usize>>,
+    track_every_knot: bool,
+
+    /// How many steps every knot has moved.
+    moves: Vec<usize>,
 }
 
-impl<const L: usize> Rope<L> {
+impl Rope {
+    fn new(config: RopeConfig) -> Self {
+        assert!(config.knots > 0, "A rope needs at least one knot");
+
+        let mut rope = Self {
+            rule: config.rule,
+            knots: vec![Position::default(); config.knots],
+            visits: vec![HashMap::default(); config.knots],
+            track_every_knot: config.track_every_knot,
+            moves: vec![0; config.knots],
+        };
+
+        for idx in 0..config.knots {
+            rope.record_visit(idx);
+        }
+
+        rope
+    }
+
+    /// Count the current position of the knot at the given index as a visit, if it is tracked.
+    fn record_visit(&mut self, idx: usize) {
+        if self.track_every_knot || idx == self.knots.len() - 1 {
+            *self.visits[idx].entry(self.knots[idx]).or_default() += 1;
+        }
+    }
+
+    /// The visits of the knot at the given index.
+    fn knot_visits(&self, idx: usize) -> &HashMap<Position, usize> {
+        assert!(
+            self.track_every_knot || idx == self.knots.len() - 1,
+            "Only the visits of the tail are tracked"
+        );
+
+        &self.visits[idx]
+    }
+
     #[cfg(test)]
     fn head(&self) -> Position {
         self.knots[0]
@@ -143,21 +288,29 @@ impl<const L: usize> Rope<L> {
 
     #[cfg(test)]
     fn tail(&self) -> Position {
-        self.knots[L - 1]
+        self.knots[self.knots.len() - 1]
     }
 
     fn apply_motion(&mut self, motion: &Motion) -> &mut Self {
         self.knots[0].apply_motion(motion);
+        self.moves[0] += 1;
+        self.record_visit(0);
 
-        for idx in 1..L {
+        for idx in 1..self.knots.len() {
             let to_follow = self.knots[idx - 1];
-            self.knots[idx].follow(&to_follow);
+            let previous = self.knots[idx];
+            self.knots[idx].follow(&to_follow, self.rule);
+
+            if self.knots[idx] != previous {
+                self.moves[idx] += 1;
+                self.record_visit(idx);
+            }
         }
 
-        self.visited_by_tail.insert(self.knots[L - 1]);
         self
     }
 
+    #[cfg(test)]
     fn apply_instruction(&mut self, instruction: &Instruction) -> &mut Self {
         for _ in 0..instruction.count {
             self.apply_motion(&instruction.motion);
@@ -166,17 +319,179 @@ impl<const L: usize> Rope<L> {
         self
     }
 
+    fn visited_by_tail(&self) -> &HashMap<Position, usize> {
+        self.knot_visits(self.knots.len() - 1)
+    }
+
     fn visited_by_tail_count(&self) -> usize {
-        self.visited_by_tail.len()
+        self.visited_by_tail().len()
+    }
+
+    /// The number of positions that the knot at the given index has visited.
+    fn visited_count(&self, idx: usize) -> usize {
+        self.knot_visits(idx).len()
+    }
+
+    /// How often the knot at the given index has visited every position.
+    fn heatmap(&self, idx: usize) -> Heatmap {
+        let visits = self.knot_visits(idx);
+        let positions = || visits.keys();
+
+        let min_x = positions().map(|position| position.x).min().unwrap();
+        let max_x = positions().map(|position| position.x).max().unwrap();
+        let min_y = positions().map(|position| position.y).min().unwrap();
+        let max_y = positions().map(|position| position.y).max().unwrap();
+
+        // The y-axis points up
+        let rows = (min_y..=max_y)
+            .rev()
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| visits.get(&Position { x, y }).copied().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+
+        Heatmap {
+            top_left: Position { x: min_x, y: max_y },
+            rows,
+        }
     }
 }
 
-impl<const L: usize> Default for Rope<L> {
-    fn default() -> Self {
-        Self {
-            knots: [Position::default(); L],
-            visited_by_tail: HashSet::default(),
+#[derive(Debug)]
+enum HeatmapError {
+    Io(io::Error),
+    Png(png::EncodingError),
+}
+
+impl Display for HeatmapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeatmapError::Io(err) => write!(f, "Failed to write heatmap: {err}"),
+            HeatmapError::Png(err) => write!(f, "Failed to encode PNG: {err}"),
+        }
+    }
+}
+
+impl From<io::Error> for HeatmapError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for HeatmapError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Png(err)
+    }
+}
+
+/// How often a knot has visited every position, as a grid starting at the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Heatmap {
+    top_left: Position,
+    rows: Vec<Vec<usize>>,
+}
+
+impl Heatmap {
+    /// Write the visits as CSV, with the x-coordinates in the first row and the y-coordinates in the first column.
+    fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        let width = self.rows[0].len() as i32;
+        let xs = (self.top_left.x..self.top_left.x + width).map(|x| x.to_string());
+        writeln!(writer, ",{}", xs.collect::<Vec<_>>().join(","))?;
+
+        for (idx, row) in self.rows.iter().enumerate() {
+            let y = self.top_left.y - idx as i32;
+            let visits: Vec<String> = row.iter().map(|visits| visits.to_string()).collect();
+            writeln!(writer, "{y},{}", visits.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the visits as grayscale PNG, with one pixel per position.
+    ///
+    /// The most visited position is white and unvisited positions are black.
+    fn write_png(&self, writer: impl Write) -> Result<(), HeatmapError> {
+        let max_visits = self
+            .rows
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or_default();
+        let pixels: Vec<u8> = self
+            .rows
+            .iter()
+            .flatten()
+            .map(|&visits| (visits * 255).checked_div(max_visits).unwrap_or_default() as u8)
+            .collect();
+
+        let mut encoder =
+            png::Encoder::new(writer, self.rows[0].len() as u32, self.rows.len() as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+
+        Ok(())
+    }
+}
+
+impl Rope {
+    /// The label of the knot in drawings, `H` for the head, `T` for the tail and the index otherwise.
+    fn knot_label(&self, idx: usize) -> char {
+        if idx == 0 {
+            'H'
+        } else if idx == self.knots.len() - 1 {
+            'T'
+        } else {
+            char::from_digit((idx % 36) as u32, 36).unwrap()
+        }
+    }
+}
+
+/// Draw the rope like in the puzzle description, with the positions visited by the tail as `#`.
+///
+/// Knots in front cover the ones behind them and the start is marked as `s`.
+impl Display for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let start = Position::default();
+        let positions = || {
+            self.knots
+                .iter()
+                .chain(self.visited_by_tail().keys())
+                .chain([&start])
+        };
+
+        let min_x = positions().map(|position| position.x).min().unwrap();
+        let max_x = positions().map(|position| position.x).max().unwrap();
+        let min_y = positions().map(|position| position.y).min().unwrap();
+        let max_y = positions().map(|position| position.y).max().unwrap();
+
+        // The y-axis points up
+        for y in (min_y..=max_y).rev() {
+            let row: String = (min_x..=max_x)
+                .map(|x| {
+                    let position = Position { x, y };
+
+                    if let Some(idx) = self.knots.iter().position(|&knot| knot == position) {
+                        self.knot_label(idx)
+                    } else if position == start {
+                        's'
+                    } else if self.visited_by_tail().contains_key(&position) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            writeln!(f, "{row}")?;
         }
+
+        Ok(())
     }
 }
 
@@ -189,32 +504,201 @@ impl Day for Day09 {
 
     fn run(&self) {
         let input = self.get_input();
+        let config = rope_config_from_args();
+
+        println!("Part 1: {}", part_1(&input, config.rule));
+        println!("Part 2: {}", part_2(&input, config.rule));
+
+        if has_flag("--animate") {
+            let delay = option_value("--delay")
+                .map(|delay| delay.parse().expect("Invalid delay"))
+                .unwrap_or(DEFAULT_ANIMATION_DELAY);
 
-        println!("Part 1: {}", part_1(&input));
-        println!("Part 2: {}", part_2(&input));
+            animate(&input, config, Duration::from_millis(delay));
+        }
+
+        if let Some(dir) = option_value("--frames") {
+            let count =
+                export_frames(&input, config, Path::new(&dir)).expect("Failed to write frames");
+            println!("Wrote {count} frames to {dir}");
+        }
+
+        let print_stats = has_flag("--knot-stats");
+        let heatmap_path = option_value("--heatmap");
+        let print_tail = option_value("--knots").is_some();
+
+        if !print_stats && heatmap_path.is_none() && !print_tail {
+            return;
+        }
+
+        // All remaining options use the same configured rope
+        let rope = simulate(&input, config);
+
+        if print_stats {
+            print_knot_stats(&rope);
+        }
+
+        if let Some(path) = heatmap_path {
+            let knot = option_value("--knot")
+                .map(|knot| knot.parse().expect("Invalid knot index"))
+                .unwrap_or(config.knots - 1);
+
+            export_heatmap(&rope, knot, &path).expect("Failed to write heatmap");
+        }
+
+        if print_tail {
+            println!(
+                "Rope with {} knots: {}",
+                config.knots,
+                rope.visited_by_tail_count()
+            );
+        }
+    }
+}
+
+/// The rope configuration from the command line, with the puzzle rules by default.
+fn rope_config_from_args() -> RopeConfig {
+    let default = RopeConfig::default();
+
+    RopeConfig {
+        knots: option_value("--knots")
+            .map(|knots| {
+                knots
+                    .parse()
+                    .ok()
+                    .filter(|&knots| knots > 0)
+                    .expect("Invalid knot count, a rope needs at least one knot")
+            })
+            .unwrap_or(default.knots),
+        rule: FollowRule {
+            adjacency: option_value("--adjacency")
+                .map(|adjacency| {
+                    adjacency
+                        .parse()
+                        .expect("Invalid adjacency, use chebyshev or manhattan")
+                })
+                .unwrap_or(default.rule.adjacency),
+            diagonal_moves: !has_flag("--no-diagonal"),
+        },
+        track_every_knot: has_flag("--knot-stats") || option_value("--heatmap").is_some(),
     }
 }
 
-fn part_1(input: &str) -> usize {
-    let mut rope = Rope::<2>::default();
+/// The time between two frames of the animation, in milliseconds.
+const DEFAULT_ANIMATION_DELAY: u64 = 50;
+
+/// Move a rope with the given configuration through all instructions.
+fn simulate(input: &str, config: RopeConfig) -> Rope {
+    simulate_steps(input, config, |_, _| {})
+}
+
+/// Move a rope through all instructions, calling `on_step` after every single step.
+fn simulate_steps(
+    input: &str,
+    config: RopeConfig,
+    mut on_step: impl FnMut(&Instruction, &Rope),
+) -> Rope {
+    let mut rope = Rope::new(config);
 
     for line in input.trim().lines() {
         let instruction: Instruction = line.parse().unwrap();
-        rope.apply_instruction(&instruction);
+
+        for _ in 0..instruction.count {
+            rope.apply_motion(&instruction.motion);
+            on_step(&instruction, &rope);
+        }
     }
 
-    rope.visited_by_tail_count()
+    rope
 }
 
-fn part_2(input: &str) -> usize {
-    let mut rope = Rope::<10>::default();
+/// Play the movement of the rope in the terminal.
+fn animate(input: &str, config: RopeConfig, delay: Duration) {
+    simulate_steps(input, config, |instruction, rope| {
+        // Clear the terminal and move the cursor to the top left
+        print!("\x1b[2J\x1b[H");
+        println!("== {instruction} ==\n\n{rope}");
+        thread::sleep(delay);
+    });
+}
 
-    for line in input.trim().lines() {
-        let instruction: Instruction = line.parse().unwrap();
-        rope.apply_instruction(&instruction);
+/// Print how many positions every knot has visited and how far it has moved.
+fn print_knot_stats(rope: &Rope) {
+    println!("\nKnot  Positions  Moves  Most visits");
+
+    for idx in 0..rope.knots.len() {
+        println!(
+            "{:>4}  {:>9}  {:>5}  {:>11}",
+            rope.knot_label(idx),
+            rope.visited_count(idx),
+            rope.moves[idx],
+            rope.knot_visits(idx).values().max().unwrap_or(&0)
+        );
+    }
+}
+
+/// Write the heatmap of the knot at the given index.
+///
+/// Files ending with `.csv` are written as CSV, all others as PNG.
+fn export_heatmap(rope: &Rope, knot: usize, path: &str) -> Result<(), HeatmapError> {
+    assert!(
+        knot < rope.knots.len(),
+        "The rope only has {} knots",
+        rope.knots.len()
+    );
+
+    let heatmap = rope.heatmap(knot);
+    let writer = BufWriter::new(File::create(path)?);
+
+    if path.ends_with(".csv") {
+        heatmap.write_csv(writer)?;
+    } else {
+        heatmap.write_png(writer)?;
     }
 
-    rope.visited_by_tail_count()
+    println!("Wrote {path}");
+    Ok(())
+}
+
+/// Write the drawing of the rope after every step to a text file in the given folder.
+///
+/// Returns the number of frames.
+fn export_frames(input: &str, config: RopeConfig, dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut frame = 0;
+    let mut result = Ok(());
+
+    simulate_steps(input, config, |instruction, rope| {
+        if result.is_ok() {
+            frame += 1;
+            result = fs::write(
+                dir.join(format!("frame_{frame:05}.txt")),
+                format!("== {instruction} ==\n\n{rope}"),
+            );
+        }
+    });
+
+    result.map(|_| frame)
+}
+
+fn part_1(input: &str, rule: FollowRule) -> usize {
+    let config = RopeConfig {
+        knots: 2,
+        rule,
+        ..RopeConfig::default()
+    };
+
+    simulate(input, config).visited_by_tail_count()
+}
+
+fn part_2(input: &str, rule: FollowRule) -> usize {
+    let config = RopeConfig {
+        knots: 10,
+        rule,
+        ..RopeConfig::default()
+    };
+
+    simulate(input, config).visited_by_tail_count()
 }
 
 #[cfg(test)]
@@ -243,7 +727,7 @@ U 20
 
     #[test]
     fn should_calculate_part_1_solution() {
-        let actual = part_1(EXAMPLE_INPUT_1);
+        let actual = part_1(EXAMPLE_INPUT_1, FollowRule::default());
 
         assert_eq!(actual, 13);
     }
@@ -252,13 +736,13 @@ U 20
     fn should_follow_across_sides() {
         let head = Position::new(3, 1);
         let mut tail = Position::new(1, 1);
-        tail.follow(&head);
+        tail.follow(&head, FollowRule::default());
 
         assert_eq!(tail, Position::new(2, 1));
 
         let head = Position::new(1, 1);
         let mut tail = Position::new(1, 3);
-        tail.follow(&head);
+        tail.follow(&head, FollowRule::default());
 
         assert_eq!(tail, Position::new(1, 2));
     }
@@ -267,20 +751,187 @@ U 20
     fn should_follow_across_diagonals() {
         let head = Position::new(4, 2);
         let mut tail = Position::new(3, 0);
-        tail.follow(&head);
+        tail.follow(&head, FollowRule::default());
 
         assert_eq!(tail, Position::new(4, 1));
 
         let head = Position::new(2, 3);
         let mut tail = Position::new(1, 1);
-        tail.follow(&head);
+        tail.follow(&head, FollowRule::default());
 
         assert_eq!(tail, Position::new(2, 2));
     }
 
+    #[test]
+    fn should_follow_with_manhattan_adjacency() {
+        let rule = FollowRule {
+            adjacency: Adjacency::Manhattan,
+            diagonal_moves: true,
+        };
+
+        // Touching diagonally is not close enough, but the tail stops next to the head
+        let head = Position::new(1, 1);
+        let mut tail = Position::new(0, 0);
+        tail.follow(&head, rule);
+
+        assert_eq!(tail, Position::new(1, 0));
+
+        let head = Position::new(2, 1);
+        let mut tail = Position::new(0, 0);
+        tail.follow(&head, rule);
+
+        assert_eq!(tail, Position::new(1, 1));
+
+        let head = Position::new(1, 0);
+        let mut tail = Position::new(0, 0);
+        tail.follow(&head, rule);
+
+        assert_eq!(tail, Position::new(0, 0));
+    }
+
+    #[test]
+    fn should_follow_without_diagonal_moves() {
+        let rule = FollowRule {
+            adjacency: Adjacency::Chebyshev,
+            diagonal_moves: false,
+        };
+
+        let head = Position::new(4, 2);
+        let mut tail = Position::new(3, 0);
+        tail.follow(&head, rule);
+
+        assert_eq!(tail, Position::new(3, 1));
+
+        let head = Position::new(2, 2);
+        let mut tail = Position::new(0, 0);
+        tail.follow(&head, rule);
+
+        assert_eq!(tail, Position::new(1, 0));
+    }
+
+    #[test]
+    fn should_simulate_any_knot_count() {
+        let rope = simulate(
+            EXAMPLE_INPUT_2,
+            RopeConfig {
+                knots: 1,
+                ..RopeConfig::default()
+            },
+        );
+
+        // A single knot is the head and the tail
+        assert_eq!(rope.head(), rope.tail());
+        assert_eq!(rope.head(), Position::new(-11, 15));
+
+        let rope = simulate(
+            EXAMPLE_INPUT_2,
+            RopeConfig {
+                knots: 10,
+                ..RopeConfig::default()
+            },
+        );
+
+        assert_eq!(rope.visited_by_tail_count(), 36);
+    }
+
+    #[test]
+    fn should_draw_rope() {
+        let rope = simulate(EXAMPLE_INPUT_1, RopeConfig::default());
+        let expected = "..##.
+...##
+.TH##
+....#
+s###.
+";
+
+        assert_eq!(rope.to_string(), expected);
+
+        let rope = simulate(
+            "R 4",
+            RopeConfig {
+                knots: 10,
+                ..RopeConfig::default()
+            },
+        );
+
+        // 4 covers 5, 6, 7, 8, 9 and s
+        assert_eq!(rope.to_string(), "4321H\n");
+    }
+
+    #[test]
+    fn should_call_back_after_every_step() {
+        let mut frames = Vec::new();
+        simulate_steps("R 2\nU 1", RopeConfig::default(), |instruction, rope| {
+            frames.push(format!("{instruction}\n{rope}"));
+        });
+
+        assert_eq!(frames, vec!["R 2\nTH\n", "R 2\nsTH\n", "U 1\n..H\nsT.\n"]);
+    }
+
+    #[test]
+    fn should_track_visits_of_every_knot() {
+        let config = RopeConfig {
+            knots: 10,
+            track_every_knot: true,
+            ..RopeConfig::default()
+        };
+        let rope = simulate(EXAMPLE_INPUT_1, config);
+
+        // The first knot behind the head moves like the tail of the short rope
+        assert_eq!(
+            rope.visited_count(1),
+            part_1(EXAMPLE_INPUT_1, FollowRule::default())
+        );
+        assert_eq!(rope.visited_count(9), 1);
+
+        // Only moving onto a position counts as a visit, and the start is the first visit
+        let rope = simulate("R 2\nL 2", config);
+
+        assert_eq!(rope.moves[..2], [4, 1]);
+        assert_eq!(rope.visits[0][&Position::new(1, 0)], 2);
+        assert_eq!(rope.visits[1][&Position::new(1, 0)], 1);
+        assert_eq!(rope.visits[9][&Position::new(0, 0)], 1);
+
+        // Without the stats, only the visits of the tail are tracked
+        let rope = simulate("R 2\nL 2", RopeConfig::default());
+
+        assert!(rope.visits[0].is_empty());
+        assert_eq!(rope.visited_by_tail_count(), 2);
+    }
+
+    #[test]
+    fn should_export_heatmap() {
+        let config = RopeConfig {
+            track_every_knot: true,
+            ..RopeConfig::default()
+        };
+        let rope = simulate("R 2\nL 2\nU 1", config);
+        let heatmap = rope.heatmap(0);
+
+        assert_eq!(heatmap.top_left, Position::new(0, 1));
+        assert_eq!(heatmap.rows, vec![vec![1, 0, 0], vec![2, 2, 1]]);
+
+        let mut csv = Vec::new();
+        heatmap.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            ",0,1,2\n1,1,0,0\n0,2,2,1\n"
+        );
+
+        let mut png = Vec::new();
+        heatmap.write_png(&mut png).unwrap();
+
+        let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!(pixels, vec![127, 0, 0, 255, 255, 127]);
+    }
+
     #[test]
     fn should_apply_instructions() {
-        let mut rope = Rope::<2>::default();
+        let mut rope = Rope::new(RopeConfig::default());
 
         // R 4
         rope.apply_instruction(&Instruction::new(Motion::Right, 4));
@@ -333,14 +984,14 @@ U 20
 
     #[test]
     fn should_calculate_part_2_solution_example_1() {
-        let actual = part_2(EXAMPLE_INPUT_1);
+        let actual = part_2(EXAMPLE_INPUT_1, FollowRule::default());
 
         assert_eq!(actual, 1);
     }
 
     #[test]
     fn should_calculate_part_2_solution_example_2() {
-        let actual = part_2(EXAMPLE_INPUT_2);
+        let actual = part_2(EXAMPLE_INPUT_2, FollowRule::default());
 
         assert_eq!(actual, 36);
     }
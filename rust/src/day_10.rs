@@ -1,6 +1,12 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Display, Write as _},
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+    str::FromStr,
+};
 
-use crate::utils::Day;
+use crate::utils::{has_flag, option_value, Day};
 
 #[derive(Debug)]
 struct ParsingError;
@@ -10,17 +16,317 @@ enum ExecutionError {
     EndOfProgram,
 }
 
+/// The width of a letter on the CRT, without the space to the next letter.
+const GLYPH_WIDTH: usize = 4;
+
+/// The height of a letter on the CRT.
+const GLYPH_HEIGHT: usize = 6;
+
+/// The letters that the CRT can display.
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// The CRT shows something that can't be read as letters.
+#[derive(Debug, PartialEq, Eq)]
+struct OcrError {
+    /// The positions of the letters that could not be recognized.
+    unrecognized: Vec<usize>,
+
+    /// The raw image of the CRT.
+    image: String,
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let positions = self
+            .unrecognized
+            .iter()
+            .map(|position| position.to_string())
+            .intersperse(", ".to_string())
+            .collect::<String>();
+
+        write!(
+            f,
+            "Unrecognized letters at positions {positions}\n{}",
+            self.image
+        )
+    }
+}
+
+/// A register of the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    X,
+    Y,
+    Z,
+    W,
+}
+
+impl FromStr for Register {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let register = match s {
+            "x" => Register::X,
+            "y" => Register::Y,
+            "z" => Register::Z,
+            "w" => Register::W,
+            _ => return Err(ParsingError),
+        };
+
+        Ok(register)
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Register::X => "x",
+            Register::Y => "y",
+            Register::Z => "z",
+            Register::W => "w",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// The values of all registers of the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Registers([i64; 4]);
+
+impl Registers {
+    fn get(&self, register: Register) -> i64 {
+        self.0[register as usize]
+    }
+
+    fn get_mut(&mut self, register: Register) -> &mut i64 {
+        &mut self.0[register as usize]
+    }
+}
+
+impl Display for Registers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "x={} y={} z={} w={}",
+            self.0[0], self.0[1], self.0[2], self.0[3]
+        )
+    }
+}
+
+/// The input of an instruction, either the value of a register or a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(Register),
+    Value(i64),
+}
+
+impl Operand {
+    fn evaluate(&self, registers: &Registers) -> i64 {
+        match self {
+            Operand::Register(register) => registers.get(*register),
+            Operand::Value(value) => *value,
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{register}"),
+            Operand::Value(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(register) = s.parse() {
+            Ok(Operand::Register(register))
+        } else {
+            s.parse().map(Operand::Value).map_err(|_| ParsingError)
+        }
+    }
+}
+
+/// The operands an instruction takes, with the function to build the instruction from them.
+#[derive(Clone, Copy)]
+enum Operands {
+    None(Instruction),
+    RegisterOperand(fn(Register, Operand) -> Instruction),
+    Offset(fn(isize) -> Instruction),
+    OperandOffset(fn(Operand, isize) -> Instruction),
+}
+
+/// The definition of an instruction in the instruction set.
+struct InstructionSpec {
+    mnemonic: &'static str,
+    operands: Operands,
+
+    /// The number of cycles it takes to execute the instruction.
+    cycles: usize,
+}
+
+impl InstructionSpec {
+    /// The number of operands of the instruction.
+    fn arity(&self) -> usize {
+        match self.operands {
+            Operands::None(_) => 0,
+            Operands::Offset(_) => 1,
+            Operands::RegisterOperand(_) | Operands::OperandOffset(_) => 2,
+        }
+    }
+
+    /// Build the instruction from its operands in the assembly.
+    fn build(&self, operands: &[&str]) -> Result<Instruction, ParsingError> {
+        let parse_offset = |offset: &str| offset.parse::<isize>().map_err(|_| ParsingError);
+
+        match (self.operands, operands) {
+            (Operands::None(instruction), []) => Ok(instruction),
+            (Operands::RegisterOperand(build), [register, operand]) => {
+                Ok(build(register.parse()?, operand.parse()?))
+            }
+            (Operands::Offset(build), [offset]) => Ok(build(parse_offset(offset)?)),
+            (Operands::OperandOffset(build), [operand, offset]) => {
+                Ok(build(operand.parse()?, parse_offset(offset)?))
+            }
+            _ => Err(ParsingError),
+        }
+    }
+
+    /// An instance of the instruction, to compare with others.
+    fn example(&self) -> Instruction {
+        match self.operands {
+            Operands::None(instruction) => instruction,
+            Operands::RegisterOperand(build) => build(Register::X, Operand::Value(0)),
+            Operands::Offset(build) => build(0),
+            Operands::OperandOffset(build) => build(Operand::Value(0), 0),
+        }
+    }
+}
+
+/// All instructions of the CPU.
+const INSTRUCTION_SET: [InstructionSpec; 8] = [
+    InstructionSpec {
+        mnemonic: "noop",
+        operands: Operands::None(Instruction::Noop),
+        cycles: 1,
+    },
+    InstructionSpec {
+        mnemonic: "add",
+        operands: Operands::RegisterOperand(Instruction::Add),
+        cycles: 2,
+    },
+    InstructionSpec {
+        mnemonic: "sub",
+        operands: Operands::RegisterOperand(Instruction::Sub),
+        cycles: 2,
+    },
+    InstructionSpec {
+        mnemonic: "mul",
+        operands: Operands::RegisterOperand(Instruction::Mul),
+        cycles: 2,
+    },
+    InstructionSpec {
+        mnemonic: "mov",
+        operands: Operands::RegisterOperand(Instruction::Mov),
+        cycles: 1,
+    },
+    InstructionSpec {
+        mnemonic: "jmp",
+        operands: Operands::Offset(Instruction::Jmp),
+        cycles: 1,
+    },
+    InstructionSpec {
+        mnemonic: "jz",
+        operands: Operands::OperandOffset(Instruction::Jz),
+        cycles: 1,
+    },
+    InstructionSpec {
+        mnemonic: "jnz",
+        operands: Operands::OperandOffset(Instruction::Jnz),
+        cycles: 1,
+    },
+];
+
+/// An instruction of the CPU.
+///
+/// The jumps are relative to the current instruction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Instruction {
     Noop,
-    AddX(i64),
+    Add(Register, Operand),
+    Sub(Register, Operand),
+    Mul(Register, Operand),
+    Mov(Register, Operand),
+    Jmp(isize),
+    Jz(Operand, isize),
+    Jnz(Operand, isize),
 }
 
 impl Instruction {
+    /// The definition of the instruction in the instruction set.
+    fn spec(&self) -> &'static InstructionSpec {
+        INSTRUCTION_SET
+            .iter()
+            .find(|spec| std::mem::discriminant(&spec.example()) == std::mem::discriminant(self))
+            .expect("Every instruction is in the instruction set")
+    }
+
+    fn mnemonic(&self) -> &'static str {
+        self.spec().mnemonic
+    }
+
+    /// The offset of the next instruction, if this is a jump.
+    fn jump_offset(&self) -> Option<isize> {
+        match self {
+            Instruction::Jmp(offset) | Instruction::Jz(_, offset) | Instruction::Jnz(_, offset) => {
+                Some(*offset)
+            }
+            _ => None,
+        }
+    }
+
+    /// The number of cycles it takes to execute the instruction.
     fn duration(&self) -> usize {
+        self.spec().cycles
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mnemonic = self.mnemonic();
+
         match self {
-            Instruction::Noop => 1,
-            Instruction::AddX(_) => 2,
+            Instruction::Noop => write!(f, "{mnemonic}"),
+            Instruction::Add(register, operand)
+            | Instruction::Sub(register, operand)
+            | Instruction::Mul(register, operand)
+            | Instruction::Mov(register, operand) => write!(f, "{mnemonic} {register} {operand}"),
+            Instruction::Jmp(offset) => write!(f, "{mnemonic} {offset}"),
+            Instruction::Jz(operand, offset) | Instruction::Jnz(operand, offset) => {
+                write!(f, "{mnemonic} {operand} {offset}")
+            }
         }
     }
 }
@@ -29,155 +335,1260 @@ impl FromStr for Instruction {
     type Err = ParsingError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut tokens = s.split_ascii_whitespace();
-
-        let instruction = match tokens.next().expect("Expected command identifier") {
-            "noop" => Self::Noop,
-            "addx" => {
-                let val = tokens
-                    .next()
-                    .expect("Expected value for addx")
-                    .parse()
-                    .expect("Expected number");
-                Self::AddX(val)
+        let tokens: Vec<&str> = s.split_ascii_whitespace().collect();
+
+        match tokens.as_slice() {
+            // The original instruction of the puzzle
+            ["addx", value] => Ok(Self::Add(
+                Register::X,
+                Operand::Value(value.parse().map_err(|_| ParsingError)?),
+            )),
+            [mnemonic, operands @ ..] => INSTRUCTION_SET
+                .iter()
+                .find(|spec| spec.mnemonic == *mnemonic && spec.arity() == operands.len())
+                .ok_or(ParsingError)?
+                .build(operands),
+            [] => Err(ParsingError),
+        }
+    }
+}
+
+/// An error in the assembly of a program.
+///
+/// The line numbers start at 1.
+/// Errors in the body of a macro are reported at the line where the macro is used.
+#[derive(Debug, PartialEq, Eq)]
+enum AssemblyError {
+    InvalidInstruction {
+        line: usize,
+        content: String,
+    },
+    UnknownLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    InvalidLabel {
+        line: usize,
+        label: String,
+    },
+    InvalidMacro {
+        line: usize,
+    },
+    UnterminatedMacro {
+        line: usize,
+        name: String,
+    },
+    MacroArguments {
+        line: usize,
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    MacroDepth {
+        line: usize,
+        name: String,
+    },
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyError::InvalidInstruction { line, content } => {
+                write!(f, "Line {line}: Invalid instruction '{content}'")
             }
-            _ => return Err(ParsingError),
+            AssemblyError::UnknownLabel { line, label } => {
+                write!(f, "Line {line}: Unknown label '{label}'")
+            }
+            AssemblyError::DuplicateLabel { line, label } => {
+                write!(f, "Line {line}: Label '{label}' is already defined")
+            }
+            AssemblyError::InvalidLabel { line, label } => {
+                write!(f, "Line {line}: Label '{label}' is not a valid name")
+            }
+            AssemblyError::InvalidMacro { line } => {
+                write!(f, "Line {line}: Expected a name for the macro")
+            }
+            AssemblyError::UnterminatedMacro { line, name } => {
+                write!(f, "Line {line}: Macro '{name}' is missing '.endm'")
+            }
+            AssemblyError::MacroArguments {
+                line,
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Line {line}: Macro '{name}' expects {expected} arguments, but got {actual}"
+            ),
+            AssemblyError::MacroDepth { line, name } => {
+                write!(f, "Line {line}: Macro '{name}' is nested too deeply")
+            }
+        }
+    }
+}
+
+/// How deep macros can be nested, to stop recursive macros.
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// A named list of instructions, that replaces every use of the name.
+///
+/// The parameters are used as `$name` in the body.
+struct Macro {
+    params: Vec<String>,
+    body: Vec<String>,
+}
+
+/// Check that a label starts with a letter or `_` and only contains letters, digits and `_`,
+/// so it can't be mistaken for an offset.
+fn validate_label(line: usize, label: &str) -> Result<(), AssemblyError> {
+    let is_identifier = label
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_identifier {
+        Ok(())
+    } else {
+        Err(AssemblyError::InvalidLabel {
+            line,
+            label: label.to_string(),
+        })
+    }
+}
+
+/// The position of the target among the tokens of a jump instruction.
+fn jump_target_position(tokens: &[&str]) -> Option<usize> {
+    match tokens.first() {
+        Some(&"jmp") => Some(1),
+        Some(&"jz" | &"jnz") => Some(2),
+        _ => None,
+    }
+}
+
+/// Expand the given line of assembly, if it uses a macro.
+///
+/// Labels in the body of a macro are only visible within each use of the macro.
+fn expand_line(
+    line: usize,
+    content: &str,
+    macros: &HashMap<String, Macro>,
+    expansion_count: &mut usize,
+    depth: usize,
+    output: &mut Vec<(usize, String)>,
+) -> Result<(), AssemblyError> {
+    let tokens: Vec<&str> = content.split_ascii_whitespace().collect();
+
+    // Put labels on their own line
+    if let Some((first, rest)) = tokens.split_first()
+        && let Some(label) = first.strip_suffix(':')
+    {
+        // Labels from macros were already checked before they got a suffix
+        if depth == 0 {
+            validate_label(line, label)?;
+        }
+
+        output.push((line, first.to_string()));
+
+        return if rest.is_empty() {
+            Ok(())
+        } else {
+            expand_line(
+                line,
+                &rest.join(" "),
+                macros,
+                expansion_count,
+                depth,
+                output,
+            )
         };
+    }
+
+    let Some((name, args)) = tokens.split_first() else {
+        return Ok(());
+    };
+
+    let Some(macro_def) = macros.get(*name) else {
+        output.push((line, content.to_string()));
+        return Ok(());
+    };
+
+    if depth >= MAX_MACRO_DEPTH {
+        return Err(AssemblyError::MacroDepth {
+            line,
+            name: name.to_string(),
+        });
+    }
 
-        Ok(instruction)
+    if args.len() != macro_def.params.len() {
+        return Err(AssemblyError::MacroArguments {
+            line,
+            name: name.to_string(),
+            expected: macro_def.params.len(),
+            actual: args.len(),
+        });
     }
+
+    *expansion_count += 1;
+    let suffix = *expansion_count;
+
+    let mut local_labels: Vec<&str> = Vec::new();
+    for body_line in macro_def.body.iter() {
+        for label in body_line
+            .split_ascii_whitespace()
+            .map_while(|token| token.strip_suffix(':'))
+        {
+            validate_label(line, label)?;
+            local_labels.push(label);
+        }
+    }
+
+    let local_label = |label: &str| {
+        local_labels
+            .contains(&label)
+            .then(|| format!("{label}.{suffix}"))
+    };
+
+    for body_line in macro_def.body.iter() {
+        let tokens: Vec<&str> = body_line.split_ascii_whitespace().collect();
+
+        // Only the definitions of labels and the targets of jumps are renamed
+        let instruction = tokens
+            .iter()
+            .position(|token| !token.ends_with(':'))
+            .unwrap_or(tokens.len());
+        let target = jump_target_position(&tokens[instruction..]).map(|pos| instruction + pos);
+
+        let expanded = tokens
+            .iter()
+            .enumerate()
+            .map(|(idx, token)| {
+                if let Some(param) = token.strip_prefix('$')
+                    && let Some(param_idx) = macro_def.params.iter().position(|p| p == param)
+                {
+                    args[param_idx].to_string()
+                } else if idx < instruction
+                    && let Some(label) = token.strip_suffix(':')
+                    && let Some(renamed) = local_label(label)
+                {
+                    format!("{renamed}:")
+                } else if Some(idx) == target
+                    && let Some(renamed) = local_label(token)
+                {
+                    renamed
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        expand_line(line, &expanded, macros, expansion_count, depth + 1, output)?;
+    }
+
+    Ok(())
+}
+
+/// Remove comments, empty lines and macro definitions and expand all uses of macros.
+///
+/// Returns the remaining lines with their line number.
+fn preprocess(s: &str) -> Result<Vec<(usize, String)>, AssemblyError> {
+    let mut lines = s.lines().enumerate().map(|(idx, content)| {
+        (
+            idx + 1,
+            content.split(';').next().unwrap_or_default().trim(),
+        )
+    });
+
+    let mut macros: HashMap<String, Macro> = HashMap::new();
+    let mut expansion_count = 0;
+    let mut output = Vec::new();
+
+    while let Some((line, content)) = lines.next() {
+        let tokens: Vec<&str> = content.split_ascii_whitespace().collect();
+
+        if let Some((&".macro", definition)) = tokens.split_first() {
+            let Some((name, params)) = definition.split_first() else {
+                return Err(AssemblyError::InvalidMacro { line });
+            };
+
+            let mut body = Vec::new();
+
+            loop {
+                let Some((_, body_line)) = lines.next() else {
+                    return Err(AssemblyError::UnterminatedMacro {
+                        line,
+                        name: name.to_string(),
+                    });
+                };
+
+                if body_line == ".endm" {
+                    break;
+                } else if !body_line.is_empty() {
+                    body.push(body_line.to_string());
+                }
+            }
+
+            macros.insert(
+                name.to_string(),
+                Macro {
+                    params: params.iter().map(|param| param.to_string()).collect(),
+                    body,
+                },
+            );
+        } else {
+            expand_line(line, content, &macros, &mut expansion_count, 0, &mut output)?;
+        }
+    }
+
+    Ok(output)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Program(Vec<Instruction>);
 
-impl Program {}
+impl Program {
+    /// Print the program as assembly that can be parsed again.
+    ///
+    /// The targets of jumps get labels and every instruction is annotated with the cycles
+    /// in which it is executed, as long as no jump is taken.
+    fn disassemble(&self) -> String {
+        let jump_target = |index: usize, instruction: &Instruction| {
+            instruction
+                .jump_offset()
+                .and_then(|offset| index.checked_add_signed(offset))
+                .filter(|&target| target <= self.0.len())
+        };
+
+        let targets: HashSet<usize> = self
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| jump_target(index, instruction))
+            .collect();
+
+        let mut output = String::new();
+        let mut cycle = 1;
+
+        for (index, instruction) in self.0.iter().enumerate() {
+            if targets.contains(&index) {
+                writeln!(output, "l{index}:").unwrap();
+            }
+
+            let mut text = instruction.to_string();
+
+            // Replace the offset of the jump by the label of its target
+            if let Some(target) = jump_target(index, instruction) {
+                let (start, _) = text.rsplit_once(' ').unwrap();
+                text = format!("{start} l{target}");
+            }
+
+            let duration = instruction.duration();
+            let cycles = if duration == 1 {
+                format!("cycle {cycle}")
+            } else {
+                format!("cycles {cycle}-{}", cycle + duration - 1)
+            };
+            cycle += duration;
+
+            writeln!(output, "    {text:<16}; {cycles}").unwrap();
+        }
+
+        if targets.contains(&self.0.len()) {
+            writeln!(output, "l{}:", self.0.len()).unwrap();
+        }
+
+        output
+    }
+}
 
 impl FromStr for Program {
+    type Err = AssemblyError;
+
+    /// Assemble a program.
+    ///
+    /// Everything after a `;` is a comment. Lines like `name:` define labels,
+    /// which can be used instead of the offset of jumps.
+    /// Macros are defined by `.macro name param...`, followed by the body and `.endm`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = preprocess(s)?;
+
+        let mut labels: HashMap<&str, usize> = HashMap::new();
+        let mut instruction_lines: Vec<(usize, &str)> = Vec::new();
+
+        for (line, content) in lines.iter() {
+            if let Some(label) = content.strip_suffix(':') {
+                if labels.insert(label, instruction_lines.len()).is_some() {
+                    return Err(AssemblyError::DuplicateLabel {
+                        line: *line,
+                        label: label.to_string(),
+                    });
+                }
+            } else {
+                instruction_lines.push((*line, content));
+            }
+        }
+
+        let instructions = instruction_lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, (line, content))| {
+                let mut tokens: Vec<&str> = content.split_ascii_whitespace().collect();
+
+                let target_position = jump_target_position(&tokens);
+
+                // Replace labels by the offset to them
+                let offset;
+                if let Some(position) = target_position
+                    && let Some(target) = tokens.get_mut(position)
+                    && target.parse::<isize>().is_err()
+                {
+                    let Some(&target_index) = labels.get(*target) else {
+                        return Err(AssemblyError::UnknownLabel {
+                            line,
+                            label: target.to_string(),
+                        });
+                    };
+
+                    offset = (target_index as isize - index as isize).to_string();
+                    *target = &offset;
+                }
+
+                tokens
+                    .join(" ")
+                    .parse()
+                    .map_err(|_| AssemblyError::InvalidInstruction {
+                        line,
+                        content: content.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<Instruction>, AssemblyError>>()?;
+
+        Ok(Self(instructions))
+    }
+}
+
+/// What happens to the part of the sprite that is outside of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpriteEdge {
+    /// The part outside of the screen is not drawn.
+    Clip,
+
+    /// The part outside of the screen is drawn on the other side of the row.
+    Wrap,
+}
+
+/// The geometry of the CRT and its sprite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CrtConfig {
+    width: usize,
+    height: usize,
+
+    /// The number of pixels the sprite covers, centered on the x register.
+    ///
+    /// For even widths, the sprite extends further to the right.
+    sprite_width: usize,
+    sprite_edge: SpriteEdge,
+}
+
+impl Default for CrtConfig {
+    fn default() -> Self {
+        Self {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+            sprite_edge: SpriteEdge::Clip,
+        }
+    }
+}
+
+impl CrtConfig {
+    /// The first and last column of the sprite at the given position.
+    fn sprite(&self, x: i64) -> (i64, i64) {
+        let start = x.saturating_sub((self.sprite_width as i64 - 1) / 2);
+
+        (start, start.saturating_add(self.sprite_width as i64 - 1))
+    }
+
+    /// Determine if the sprite at the given position covers the column.
+    fn sprite_covers(&self, x: i64, col: usize) -> bool {
+        let (start, _) = self.sprite(x);
+        // Wide enough for any value of x
+        let offset = col as i128 - start as i128;
+
+        match self.sprite_edge {
+            SpriteEdge::Clip => (0..self.sprite_width as i128).contains(&offset),
+            SpriteEdge::Wrap => offset.rem_euclid(self.width as i128) < self.sprite_width as i128,
+        }
+    }
+}
+
+/// The cycles during which the signal strength is measured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SignalCycles {
+    /// Every `interval` cycles, starting with the cycle `first`.
+    Periodic { first: usize, interval: usize },
+
+    /// Only the given cycles.
+    List(Vec<usize>),
+}
+
+impl Default for SignalCycles {
+    fn default() -> Self {
+        Self::Periodic {
+            first: 20,
+            interval: 40,
+        }
+    }
+}
+
+impl SignalCycles {
+    fn contains(&self, cycle: usize) -> bool {
+        match self {
+            SignalCycles::Periodic { first, interval } => {
+                cycle >= *first && (cycle - first).is_multiple_of(*interval)
+            }
+            SignalCycles::List(cycles) => cycles.contains(&cycle),
+        }
+    }
+}
+
+impl FromStr for SignalCycles {
     type Err = ParsingError;
 
+    /// Parse either periodic cycles like `20+40` or a list of cycles like `20,60,100`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let instructions: Result<Vec<Instruction>, ParsingError> =
-            s.lines().map(|line| line.parse::<Instruction>()).collect();
+        if let Some((first, interval)) = s.split_once('+') {
+            let first = first.trim().parse().map_err(|_| ParsingError)?;
+            let interval = interval.trim().parse().map_err(|_| ParsingError)?;
+
+            if interval == 0 {
+                return Err(ParsingError);
+            }
+
+            Ok(Self::Periodic { first, interval })
+        } else {
+            s.split(',')
+                .map(|cycle| cycle.trim().parse().map_err(|_| ParsingError))
+                .collect::<Result<Vec<usize>, ParsingError>>()
+                .map(Self::List)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClockCircuit {
+    program: Program,
+    program_counter: usize,
+    cycle_counter: usize,
+    registers: Registers,
+    buffer: Option<(Instruction, usize)>,
+    config: CrtConfig,
+    crt: Vec<Vec<bool>>,
+
+    /// The state of the most recent cycles, if tracing is enabled.
+    trace: Option<Trace>,
+}
+
+impl ClockCircuit {
+    fn new(program: Program, config: CrtConfig) -> Self {
+        Self {
+            program,
+            program_counter: 0,
+            cycle_counter: 0,
+            registers: Registers([1, 0, 0, 0]),
+            buffer: None,
+            config,
+            crt: vec![vec![false; config.width]; config.height],
+            trace: None,
+        }
+    }
+
+    /// Record the state of the following cycles.
+    fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(|| Trace::new(TRACE_CAPACITY));
+    }
+
+    /// The value of the x register, which controls the sprite position.
+    fn x(&self) -> i64 {
+        self.registers.get(Register::X)
+    }
+
+    fn execute(&mut self, instruction: &Instruction) {
+        let registers = &mut self.registers;
+        let mut jump = 1;
+
+        match *instruction {
+            Instruction::Noop => (),
+            // The registers wrap around on overflow
+            Instruction::Add(register, operand) => {
+                let value = operand.evaluate(registers);
+                *registers.get_mut(register) = registers.get(register).wrapping_add(value)
+            }
+            Instruction::Sub(register, operand) => {
+                let value = operand.evaluate(registers);
+                *registers.get_mut(register) = registers.get(register).wrapping_sub(value)
+            }
+            Instruction::Mul(register, operand) => {
+                let value = operand.evaluate(registers);
+                *registers.get_mut(register) = registers.get(register).wrapping_mul(value)
+            }
+            Instruction::Mov(register, operand) => {
+                *registers.get_mut(register) = operand.evaluate(registers)
+            }
+            Instruction::Jmp(offset) => jump = offset,
+            Instruction::Jz(operand, offset) => {
+                if operand.evaluate(registers) == 0 {
+                    jump = offset;
+                }
+            }
+            Instruction::Jnz(operand, offset) => {
+                if operand.evaluate(registers) != 0 {
+                    jump = offset;
+                }
+            }
+        };
+
+        // Jumping outside of the program ends it
+        self.program_counter = self
+            .program_counter
+            .checked_add_signed(jump)
+            .unwrap_or(usize::MAX);
+    }
+
+    /// The position of the beam, as row and column.
+    ///
+    /// After the last row, the beam starts again at the top.
+    fn crt_position(&self) -> (usize, usize) {
+        let row = (self.cycle_counter - 1) / self.config.width % self.config.height;
+        let col = (self.cycle_counter - 1) % self.config.width;
+
+        (row, col)
+    }
+
+    /// The start of a cycle tick.
+    ///
+    /// Starts a new instruction if none is currently running.
+    fn tick_start(&mut self) -> Result<(), ExecutionError> {
+        self.cycle_counter += 1;
+
+        if self.buffer.is_none() {
+            let next_instruction =
+                if let Some(instruction) = self.program.0.get(self.program_counter) {
+                    instruction
+                } else {
+                    return Err(ExecutionError::EndOfProgram);
+                };
+
+            self.buffer = Some((*next_instruction, next_instruction.duration()));
+        }
+
+        Ok(())
+    }
+
+    /// The middle of the cycle tick.
+    ///
+    /// Draws a pixel on the CRT and returns the value of x.
+    fn tick_middle(&mut self) -> Result<i64, ExecutionError> {
+        let (row, col) = self.crt_position();
+
+        // Only draw if the pixel is within the sprite
+        if self.config.sprite_covers(self.x(), col) {
+            self.crt[row][col] = true;
+        }
+
+        if let Some(trace) = &mut self.trace
+            && let Some((instruction, remaining)) = self.buffer
+        {
+            trace.push(TraceEntry {
+                cycle: self.cycle_counter,
+                program_counter: self.program_counter,
+                instruction,
+                remaining_cycles: remaining,
+                registers: self.registers,
+                sprite: self.config.sprite(self.registers.get(Register::X)),
+                pixel: (row, col),
+                lit: self.crt[row][col],
+            });
+        }
+
+        Ok(self.x())
+    }
+
+    /// The end of a cycle tick.
+    ///
+    /// Processes instructions in the buffer and executes them once they are done.
+    fn tick_end(&mut self) -> Result<(), ExecutionError> {
+        if let Some((instruction, remaining)) = self.buffer {
+            let remaining = remaining.wrapping_sub(1);
+
+            if remaining == 0 {
+                self.execute(&instruction);
+                self.buffer = None;
+            } else {
+                self.buffer = Some((instruction, remaining));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes a single tick and returns the value of x.
+    fn tick(&mut self) -> Result<i64, ExecutionError> {
+        self.tick_start()?;
+        let x = self.tick_middle()?;
+        self.tick_end()?;
+
+        Ok(x)
+    }
+
+    /// Recognize the letter at the given position on the CRT.
+    ///
+    /// The column after the letter separates it from the next one and has to be blank.
+    fn read_letter(&self, position: usize) -> Option<char> {
+        let start_col = position * (GLYPH_WIDTH + 1);
+        let separator_col = start_col + GLYPH_WIDTH;
+
+        if self
+            .crt
+            .iter()
+            .any(|row| row.get(separator_col).copied().unwrap_or_default())
+        {
+            return None;
+        }
+
+        FONT.iter()
+            .find(|(_, glyph)| {
+                glyph.iter().enumerate().all(|(row, glyph_row)| {
+                    let Some(crt_row) = self.crt.get(row) else {
+                        return false;
+                    };
+
+                    glyph_row
+                        .chars()
+                        .zip(crt_row[start_col..start_col + GLYPH_WIDTH].iter())
+                        .all(|(glyph_pixel, &pixel)| (glyph_pixel == '#') == pixel)
+                })
+            })
+            .map(|(letter, _)| *letter)
+    }
+
+    /// Read the letters displayed on the CRT.
+    fn read_letters(&self) -> Result<String, OcrError> {
+        let letter_count = self.config.width / (GLYPH_WIDTH + 1);
+        let letters: Vec<Option<char>> = (0..letter_count)
+            .map(|position| self.read_letter(position))
+            .collect();
+
+        let unrecognized: Vec<usize> = letters
+            .iter()
+            .enumerate()
+            .filter(|(_, letter)| letter.is_none())
+            .map(|(position, _)| position)
+            .collect();
+
+        if unrecognized.is_empty() && self.config.height == GLYPH_HEIGHT {
+            Ok(letters.into_iter().flatten().collect())
+        } else {
+            Err(OcrError {
+                unrecognized,
+                image: format!("{self}"),
+            })
+        }
+    }
+}
+
+impl Display for ClockCircuit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display: String = self
+            .crt
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&pixel| if pixel { "#" } else { "." })
+                    .collect::<String>()
+            })
+            .intersperse("\n".to_string())
+            .collect();
+
+        write!(f, "{display}")
+    }
+}
+
+/// The colors of the exported images, as RGB values.
+///
+/// The index of each color is used as pixel value.
+const IMAGE_PALETTE: [u8; 12] = [
+    20, 20, 40, // Dark pixel
+    255, 255, 110, // Lit pixel
+    40, 160, 40, // Sprite
+    220, 30, 30, // Beam
+];
+
+const DARK_PIXEL: u8 = 0;
+const LIT_PIXEL: u8 = 1;
+const SPRITE_PIXEL: u8 = 2;
+const BEAM_PIXEL: u8 = 3;
+
+/// The delay between the frames of the animation, in hundredths of a second.
+const FRAME_DELAY: u16 = 5;
+
+/// The delay after the last frame of the animation, in hundredths of a second.
+const FINAL_FRAME_DELAY: u16 = 300;
+
+#[derive(Debug)]
+enum ImageError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+
+    /// The animation needs the trace of the clock circuit.
+    MissingTrace,
+
+    /// The animation needs the trace of every cycle, but the oldest ones were dropped.
+    TruncatedTrace,
+
+    /// The scaled image is empty or too large for the image format.
+    InvalidSize {
+        width: usize,
+        height: usize,
+        scale: usize,
+    },
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "Failed to write image: {err}"),
+            ImageError::Png(err) => write!(f, "Failed to encode PNG: {err}"),
+            ImageError::Gif(err) => write!(f, "Failed to encode GIF: {err}"),
+            ImageError::MissingTrace => write!(f, "The trace of the clock circuit is not enabled"),
+            ImageError::TruncatedTrace => write!(
+                f,
+                "The program runs for more than {TRACE_CAPACITY} cycles, which is too long to animate"
+            ),
+            ImageError::InvalidSize {
+                width,
+                height,
+                scale,
+            } => write!(
+                f,
+                "Can't write an image of {width}x{height} pixels scaled by {scale}"
+            ),
+        }
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Png(err)
+    }
+}
+
+impl From<gif::EncodingError> for ImageError {
+    fn from(err: gif::EncodingError) -> Self {
+        Self::Gif(err)
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Enlarge the image, so that every pixel becomes a square of `scale` by `scale` pixels.
+///
+/// Returns the pixels row by row.
+fn scale_image(image: &[Vec<u8>], scale: usize) -> Vec<u8> {
+    image
+        .iter()
+        .flat_map(|row| {
+            let scaled_row: Vec<u8> = row
+                .iter()
+                .flat_map(|&pixel| std::iter::repeat_n(pixel, scale))
+                .collect();
+
+            std::iter::repeat_n(scaled_row, scale).flatten()
+        })
+        .collect()
+}
+
+impl ClockCircuit {
+    /// The pixels of the CRT as indices into the image palette.
+    fn image(&self) -> Vec<Vec<u8>> {
+        self.crt
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&lit| if lit { LIT_PIXEL } else { DARK_PIXEL })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The frames of the animation of the CRT, one for every traced cycle.
+    ///
+    /// Every frame shows the pixels drawn so far, the sprite in the current row
+    /// and the pixel that the beam is drawing.
+    fn animation_frames(&self) -> Result<Vec<Vec<Vec<u8>>>, ImageError> {
+        let trace = self.trace.as_ref().ok_or(ImageError::MissingTrace)?;
+
+        if trace.dropped > 0 {
+            return Err(ImageError::TruncatedTrace);
+        }
+
+        let mut image = vec![vec![DARK_PIXEL; self.config.width]; self.config.height];
+
+        let frames = trace
+            .entries
+            .iter()
+            .map(|entry| {
+                let (row, col) = entry.pixel;
+
+                if entry.lit {
+                    image[row][col] = LIT_PIXEL;
+                }
+
+                let mut frame = image.clone();
+                let x = entry.registers.get(Register::X);
+
+                for (sprite_col, pixel) in frame[row].iter_mut().enumerate() {
+                    if *pixel == DARK_PIXEL && self.config.sprite_covers(x, sprite_col) {
+                        *pixel = SPRITE_PIXEL;
+                    }
+                }
+
+                frame[row][col] = BEAM_PIXEL;
+                frame
+            })
+            .collect();
+
+        Ok(frames)
+    }
 
-        Ok(Self(instructions?))
+    /// The width and height of the image, scaled by the given factor.
+    ///
+    /// Fails if the image would be empty or too large for the image format.
+    fn image_size<T: TryFrom<usize>>(&self, scale: usize) -> Result<(T, T), ImageError> {
+        let CrtConfig { width, height, .. } = self.config;
+        let scaled = |size: usize| {
+            size.checked_mul(scale)
+                .filter(|&size| size > 0)
+                .and_then(|size| T::try_from(size).ok())
+        };
+
+        scaled(width)
+            .zip(scaled(height))
+            .ok_or(ImageError::InvalidSize {
+                width,
+                height,
+                scale,
+            })
+    }
+
+    /// Write the CRT as PNG image, scaled by the given factor.
+    fn write_png(&self, writer: impl Write, scale: usize) -> Result<(), ImageError> {
+        let (width, height) = self.image_size::<u32>(scale)?;
+
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(&IMAGE_PALETTE[..]);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&scale_image(&self.image(), scale))?;
+
+        Ok(())
+    }
+
+    /// Write an animated GIF of the CRT being drawn, scaled by the given factor.
+    ///
+    /// The trace of the clock circuit has to be enabled.
+    fn write_gif(&self, writer: impl Write, scale: usize) -> Result<(), ImageError> {
+        let (width, height) = self.image_size::<u16>(scale)?;
+
+        let mut encoder = gif::Encoder::new(writer, width, height, &IMAGE_PALETTE)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        let frames = self.animation_frames()?;
+        let final_frame = self.image();
+
+        for (frame, delay) in frames
+            .iter()
+            .map(|frame| (frame, FRAME_DELAY))
+            .chain(std::iter::once((&final_frame, FINAL_FRAME_DELAY)))
+        {
+            let mut frame =
+                gif::Frame::from_indexed_pixels(width, height, &scale_image(frame, scale), None);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The most cycles that a trace keeps, older cycles are dropped.
+const TRACE_CAPACITY: usize = 100_000;
+
+/// The state of the clock circuit during the most recent cycles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Trace {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+
+    /// The number of older cycles that didn't fit anymore.
+    dropped: usize,
+}
+
+impl Trace {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+
+        self.entries.push_back(entry);
+    }
+}
+
+/// The state of the clock circuit during a single cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TraceEntry {
+    cycle: usize,
+    program_counter: usize,
+
+    /// The instruction that is currently executed.
+    instruction: Instruction,
+
+    /// The cycles the instruction still needs, including this one.
+    remaining_cycles: usize,
+
+    registers: Registers,
+
+    /// The first and last column covered by the sprite.
+    sprite: (i64, i64),
+
+    /// The position of the pixel drawn on the CRT, as row and column.
+    pixel: (usize, usize),
+
+    /// Whether the drawn pixel is lit.
+    lit: bool,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cycle {:>3} | pc {:>3} | {:<12} {} left | {} | sprite {}..={} | pixel ({}, {}) {}",
+            self.cycle,
+            self.program_counter,
+            self.instruction.to_string(),
+            self.remaining_cycles,
+            self.registers,
+            self.sprite.0,
+            self.sprite.1,
+            self.pixel.0,
+            self.pixel.1,
+            if self.lit { '#' } else { '.' }
+        )
+    }
+}
+
+/// A condition to pause the execution in the debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+    /// Pause during the given cycle.
+    Cycle(usize),
+
+    /// Pause during a cycle in which the register changed to the given value.
+    Register(Register, i64),
+}
+
+impl Breakpoint {
+    /// Determine if the breakpoint is hit, based on the current and the previous cycle.
+    fn is_hit(&self, entry: &TraceEntry, previous: Option<&TraceEntry>) -> bool {
+        match *self {
+            Breakpoint::Cycle(cycle) => entry.cycle == cycle,
+            Breakpoint::Register(register, value) => {
+                entry.registers.get(register) == value
+                    && previous.is_none_or(|previous| previous.registers.get(register) != value)
+            }
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Cycle(cycle) => write!(f, "cycle {cycle}"),
+            Breakpoint::Register(register, value) => write!(f, "{register} = {value}"),
+        }
     }
 }
 
-#[derive(Debug)]
-struct ClockCircuit<const W: usize, const H: usize> {
-    program: Program,
-    program_counter: usize,
-    cycle_counter: usize,
-    x: i64,
-    buffer: Option<(Instruction, usize)>,
-    crt: [[bool; W]; H],
+const DEBUGGER_HELP: &str = "Commands:
+  step [count]          Execute the given number of cycles (default 1)
+  continue              Execute until a breakpoint is hit or the program ends
+  break cycle <cycle>   Pause during the given cycle
+  break <reg> <value>   Pause when the register changes to the given value
+  delete                Remove all breakpoints
+  crt                   Show the CRT
+  help                  Show this help
+  quit                  Stop debugging";
+
+/// Executes a program on the clock circuit cycle by cycle.
+struct Debugger {
+    clock_circuit: ClockCircuit,
+    breakpoints: Vec<Breakpoint>,
 }
 
-impl<const W: usize, const H: usize> ClockCircuit<W, H> {
-    fn new(program: Program) -> Self {
+impl Debugger {
+    fn new(mut clock_circuit: ClockCircuit) -> Self {
+        clock_circuit.enable_trace();
+
         Self {
-            program,
-            program_counter: 0,
-            cycle_counter: 0,
-            x: 1,
-            buffer: None,
-            crt: [[false; W]; H],
+            clock_circuit,
+            breakpoints: Vec::new(),
         }
     }
 
-    fn execute(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Noop => (),
-            Instruction::AddX(val) => self.x += val,
-        };
-
-        self.program_counter += 1;
-    }
+    /// Execute a single cycle and return its state.
+    fn step(&mut self) -> Result<TraceEntry, ExecutionError> {
+        self.clock_circuit.tick()?;
 
-    fn crt_position(&self) -> (usize, usize) {
-        let row = (self.cycle_counter - 1) / W;
-        let col = (self.cycle_counter - 1) % W;
+        let entry = self
+            .clock_circuit
+            .trace
+            .as_ref()
+            .and_then(|trace| trace.entries.back())
+            .expect("The trace is enabled in the debugger");
 
-        (row, col)
+        Ok(*entry)
     }
 
-    /// The start of a cycle tick.
+    /// Execute cycles until a breakpoint is hit.
     ///
-    /// Starts a new instruction if none is currently running.
-    fn tick_start(&mut self) -> Result<(), ExecutionError> {
-        self.cycle_counter += 1;
+    /// Returns the state of the last cycle and the breakpoint that was hit.
+    fn resume(&mut self) -> Result<(TraceEntry, Breakpoint), ExecutionError> {
+        let mut previous = self
+            .clock_circuit
+            .trace
+            .as_ref()
+            .and_then(|trace| trace.entries.back())
+            .copied();
 
-        if self.buffer.is_none() {
-            let next_instruction =
-                if let Some(instruction) = self.program.0.get(self.program_counter) {
-                    instruction
-                } else {
-                    return Err(ExecutionError::EndOfProgram);
-                };
+        loop {
+            let entry = self.step()?;
 
-            self.buffer = Some((*next_instruction, next_instruction.duration()));
-        }
+            if let Some(breakpoint) = self
+                .breakpoints
+                .iter()
+                .find(|breakpoint| breakpoint.is_hit(&entry, previous.as_ref()))
+            {
+                return Ok((entry, *breakpoint));
+            }
 
-        Ok(())
+            previous = Some(entry);
+        }
     }
 
-    /// The middle of the cycle tick.
+    /// Parse and execute a single debugger command.
     ///
-    /// Draws a pixel on the CRT and returns the value of x.
-    fn tick_middle(&mut self) -> Result<i64, ExecutionError> {
-        let (row, col) = self.crt_position();
+    /// Returns `false` if the session should end.
+    fn execute_command(&mut self, command: &str, output: &mut impl Write) -> io::Result<bool> {
+        let tokens: Vec<&str> = command.split_ascii_whitespace().collect();
 
-        // Only draw if the pixel is within the sprite
-        if self.x - 1 <= col as i64 && self.x + 1 >= col as i64 {
-            self.crt[row][col] = true;
+        match tokens.as_slice() {
+            [] => (),
+            ["step" | "s"] => self.print_steps(1, output)?,
+            ["step" | "s", count] => match count.parse() {
+                Ok(count) => self.print_steps(count, output)?,
+                Err(_) => writeln!(output, "Invalid count '{count}'")?,
+            },
+            ["continue" | "c"] => match self.resume() {
+                Ok((entry, breakpoint)) => {
+                    writeln!(output, "Hit breakpoint {breakpoint}")?;
+                    writeln!(output, "{entry}")?;
+                }
+                Err(ExecutionError::EndOfProgram) => writeln!(output, "End of program")?,
+            },
+            ["break", "cycle", cycle] => match cycle.parse() {
+                Ok(cycle) => self.add_breakpoint(Breakpoint::Cycle(cycle), output)?,
+                Err(_) => writeln!(output, "Invalid cycle '{cycle}'")?,
+            },
+            ["break", register, value] => match (register.parse(), value.parse()) {
+                (Ok(register), Ok(value)) => {
+                    self.add_breakpoint(Breakpoint::Register(register, value), output)?
+                }
+                _ => writeln!(output, "Invalid breakpoint '{register} {value}'")?,
+            },
+            ["delete"] => {
+                self.breakpoints.clear();
+                writeln!(output, "Removed all breakpoints")?;
+            }
+            ["crt"] => writeln!(output, "{}", self.clock_circuit)?,
+            ["help" | "h"] => writeln!(output, "{DEBUGGER_HELP}")?,
+            ["quit" | "q"] => return Ok(false),
+            _ => writeln!(output, "Unknown command '{command}', try 'help'")?,
         }
 
-        Ok(self.x)
+        Ok(true)
     }
 
-    /// The end of a cycle tick.
-    ///
-    /// Processes instructions in the buffer and executes them once they are done.
-    fn tick_end(&mut self) -> Result<(), ExecutionError> {
-        if let Some((instruction, remaining)) = self.buffer {
-            let remaining = remaining.wrapping_sub(1);
-
-            if remaining == 0 {
-                self.execute(&instruction);
-                self.buffer = None;
-            } else {
-                self.buffer = Some((instruction, remaining));
+    fn print_steps(&mut self, count: usize, output: &mut impl Write) -> io::Result<()> {
+        for _ in 0..count {
+            match self.step() {
+                Ok(entry) => writeln!(output, "{entry}")?,
+                Err(ExecutionError::EndOfProgram) => {
+                    writeln!(output, "End of program")?;
+                    break;
+                }
             }
         }
 
         Ok(())
     }
 
-    /// Executes a single tick and returns the value of x.
-    fn tick(&mut self) -> Result<i64, ExecutionError> {
-        self.tick_start()?;
-        let x = self.tick_middle()?;
-        self.tick_end()?;
-
-        Ok(x)
+    fn add_breakpoint(
+        &mut self,
+        breakpoint: Breakpoint,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        self.breakpoints.push(breakpoint);
+        writeln!(output, "Added breakpoint {breakpoint}")
     }
-}
 
-impl<const W: usize, const H: usize> Display for ClockCircuit<W, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let display: String = self
-            .crt
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .map(|&pixel| if pixel { "#" } else { "." })
-                    .collect::<String>()
-            })
-            .intersperse("\n".to_string())
-            .collect();
+    /// Run an interactive debugging session, reading one command per line.
+    fn repl(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        writeln!(output, "{DEBUGGER_HELP}")?;
 
-        write!(f, "{display}")
+        loop {
+            write!(output, "> ")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+
+            if !self.execute_command(&line, &mut output)? {
+                return Ok(());
+            }
+        }
     }
 }
 
@@ -190,48 +1601,87 @@ impl Day for Day10 {
 
     fn run(&self) {
         let input = self.get_input();
+        let config = crt_config_from_args();
 
-        println!("Part 1: {}", part_1(&input));
-        println!("Part 2:\n{}", part_2(&input));
-    }
-}
-
-fn part_1(input: &str) -> i64 {
-    let mut signal_strength: i64 = 0;
+        if has_flag("--debug") {
+            let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+            let mut debugger = Debugger::new(ClockCircuit::new(program, config));
+            debugger
+                .repl(io::stdin().lock(), io::stdout())
+                .expect("Failed to run the debugger");
+            return;
+        }
 
-    let program: Program = input.parse().unwrap();
-    let mut clock_circuit = ClockCircuit::<40, 6>::new(program);
+        if has_flag("--disassemble") {
+            let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+            print!("{}", program.disassemble());
+            return;
+        }
 
-    let mut x = 1;
+        if let Some(path) = option_value("--png") {
+            export_image(&input, &path, config, ClockCircuit::write_png)
+                .expect("Failed to export PNG");
+        }
 
-    for _ in 0..20 {
-        if let Ok(new_x) = clock_circuit.tick() {
-            x = new_x;
-        } else {
-            break;
+        if let Some(path) = option_value("--gif") {
+            export_image(&input, &path, config, ClockCircuit::write_gif)
+                .expect("Failed to export GIF");
         }
-    }
 
-    signal_strength += clock_circuit.cycle_counter as i64 * x;
+        if has_flag("--trace") {
+            let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+            let mut clock_circuit = ClockCircuit::new(program, config);
+            clock_circuit.enable_trace();
 
-    'outer: loop {
-        for _ in 0..40 {
-            if let Ok(new_x) = clock_circuit.tick() {
-                x = new_x;
-            } else {
-                break 'outer;
+            while clock_circuit.tick().is_ok() {}
+
+            if let Some(trace) = clock_circuit.trace {
+                if trace.dropped > 0 {
+                    println!("({} earlier cycles not shown)", trace.dropped);
+                }
+
+                for entry in trace.entries {
+                    println!("{entry}");
+                }
             }
+            println!();
+        }
+
+        let signal_cycles = option_value("--signal-cycles")
+            .map(|cycles| cycles.parse().expect("Invalid signal cycles"))
+            .unwrap_or_default();
+
+        println!("Part 1: {}", part_1(&input, config, &signal_cycles));
+
+        match part_2(&input, config) {
+            Ok(letters) => println!("Part 2: {letters}"),
+            Err(err) => println!("Part 2: {err}"),
         }
+    }
+}
+
+/// The sum of the signal strengths during the given cycles.
+fn part_1(input: &str, config: CrtConfig, signal_cycles: &SignalCycles) -> i64 {
+    let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+    let mut clock_circuit = ClockCircuit::new(program, config);
+
+    let mut signal_strength: i64 = 0;
 
-        signal_strength += clock_circuit.cycle_counter as i64 * x;
+    while let Ok(x) = clock_circuit.tick() {
+        if signal_cycles.contains(clock_circuit.cycle_counter) {
+            // Wraps around like the registers
+            signal_strength =
+                signal_strength.wrapping_add((clock_circuit.cycle_counter as i64).wrapping_mul(x));
+        }
     }
 
     signal_strength
 }
 
-fn part_2(input: &str) -> String {
-    let program: Program = input.parse().unwrap();
-    let mut clock_circuit = ClockCircuit::<40, 6>::new(program);
+/// Run the program and read the letters on the CRT.
+fn part_2(input: &str, config: CrtConfig) -> Result<String, OcrError> {
+    let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+    let mut clock_circuit = ClockCircuit::new(program, config);
 
     loop {
         if clock_circuit.tick().is_err() {
@@ -239,11 +1689,70 @@ fn part_2(input: &str) -> String {
         }
     }
 
-    format!("{clock_circuit}")
+    clock_circuit.read_letters()
+}
+
+/// Read the geometry of the CRT from the command line options.
+///
+/// Options that are not given keep their default value.
+fn crt_config_from_args() -> CrtConfig {
+    let default = CrtConfig::default();
+    let size_option = |option: &str, default: usize| {
+        option_value(option)
+            .map(|value| match value.parse() {
+                Ok(size) if size > 0 => size,
+                _ => panic!("Invalid value for {option}: {value}"),
+            })
+            .unwrap_or(default)
+    };
+
+    CrtConfig {
+        width: size_option("--width", default.width),
+        height: size_option("--height", default.height),
+        sprite_width: size_option("--sprite-width", default.sprite_width),
+        sprite_edge: if has_flag("--wrap-sprite") {
+            SpriteEdge::Wrap
+        } else {
+            SpriteEdge::Clip
+        },
+    }
+}
+
+/// The factor by which the exported images are enlarged.
+const DEFAULT_IMAGE_SCALE: usize = 10;
+
+/// Run the program and export the CRT to the given file with the given image writer.
+///
+/// The scale of the image can be set with `--scale`.
+fn export_image(
+    input: &str,
+    path: &str,
+    config: CrtConfig,
+    write_image: impl Fn(&ClockCircuit, BufWriter<File>, usize) -> Result<(), ImageError>,
+) -> Result<(), ImageError> {
+    let scale = option_value("--scale")
+        .map(|value| match value.parse() {
+            Ok(scale) if scale > 0 => scale,
+            _ => panic!("Invalid value for --scale: {value}"),
+        })
+        .unwrap_or(DEFAULT_IMAGE_SCALE);
+
+    let program: Program = input.parse().unwrap_or_else(|err| panic!("{err}"));
+    let mut clock_circuit = ClockCircuit::new(program, config);
+    clock_circuit.enable_trace();
+
+    while clock_circuit.tick().is_ok() {}
+
+    write_image(&clock_circuit, BufWriter::new(File::create(path)?), scale)?;
+    println!("Wrote {path}");
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
     const EXAMPLE_INPUT: &'static str = "addx 15
@@ -394,24 +1903,546 @@ noop
 noop
 ";
 
+    #[test]
+    fn should_parse_instructions() {
+        let program: Program = "noop
+addx -3
+add y x
+sub z 2
+mul w -1
+mov x y
+jmp -2
+jz y 3
+jnz 1 -6"
+            .parse()
+            .unwrap();
+
+        let expected = Program(vec![
+            Instruction::Noop,
+            Instruction::Add(Register::X, Operand::Value(-3)),
+            Instruction::Add(Register::Y, Operand::Register(Register::X)),
+            Instruction::Sub(Register::Z, Operand::Value(2)),
+            Instruction::Mul(Register::W, Operand::Value(-1)),
+            Instruction::Mov(Register::X, Operand::Register(Register::Y)),
+            Instruction::Jmp(-2),
+            Instruction::Jz(Operand::Register(Register::Y), 3),
+            Instruction::Jnz(Operand::Value(1), -6),
+        ]);
+
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn should_execute_loops() {
+        let program: Program = "mov y 3
+mul x 2
+sub y 1
+jnz y -2
+add z x"
+            .parse()
+            .unwrap();
+        let mut clock_circuit = ClockCircuit::new(program, CrtConfig::default());
+
+        let mut cycles = 0;
+        while clock_circuit.tick().is_ok() {
+            cycles += 1;
+        }
+
+        assert_eq!(clock_circuit.x(), 8);
+        assert_eq!(clock_circuit.registers, Registers([8, 0, 8, 0]));
+        assert_eq!(cycles, 1 + 3 * (2 + 2 + 1) + 2);
+    }
+
+    #[test]
+    fn should_run_past_the_crt() {
+        // Squares x until it overflows, for much longer than the CRT has pixels
+        let program: Program = "mov x 3
+mov y 300
+mul x x
+sub y 1
+jnz y -2"
+            .parse()
+            .unwrap();
+        let mut clock_circuit = ClockCircuit::new(program, CrtConfig::default());
+
+        let mut cycles = 0;
+        while clock_circuit.tick().is_ok() {
+            cycles += 1;
+        }
+
+        assert_eq!(cycles, 2 + 300 * (2 + 2 + 1));
+        assert_eq!(clock_circuit.registers.get(Register::Y), 0);
+    }
+
+    #[test]
+    fn should_reject_invalid_instructions() {
+        assert!("addx".parse::<Instruction>().is_err());
+        assert!("addx five".parse::<Instruction>().is_err());
+        assert!("noop 3".parse::<Instruction>().is_err());
+        assert!("mul v 3".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn should_define_instructions_in_instruction_set() {
+        for spec in INSTRUCTION_SET.iter() {
+            let instruction = spec.example();
+
+            assert_eq!(instruction.mnemonic(), spec.mnemonic);
+            assert_eq!(instruction.duration(), spec.cycles);
+            assert_eq!(
+                instruction.to_string().parse::<Instruction>().unwrap(),
+                instruction
+            );
+            assert_eq!(
+                instruction.to_string().split_ascii_whitespace().count(),
+                1 + spec.arity()
+            );
+        }
+    }
+
+    #[test]
+    fn should_assemble_program() {
+        let program: Program = "; Multiply x by 8
+.macro double reg
+    add $reg $reg
+.endm
+
+.macro countdown reg count
+    mov $reg $count
+loop:
+    sub $reg 1 ; Labels in macros are local
+    jnz $reg loop
+.endm
+
+start: mov y 3
+repeat:
+    double x
+    sub y 1
+    jnz y repeat
+    countdown z 2
+    jmp end
+    noop
+end:"
+            .parse()
+            .unwrap();
+
+        let expected = Program(vec![
+            Instruction::Mov(Register::Y, Operand::Value(3)),
+            Instruction::Add(Register::X, Operand::Register(Register::X)),
+            Instruction::Sub(Register::Y, Operand::Value(1)),
+            Instruction::Jnz(Operand::Register(Register::Y), -2),
+            Instruction::Mov(Register::Z, Operand::Value(2)),
+            Instruction::Sub(Register::Z, Operand::Value(1)),
+            Instruction::Jnz(Operand::Register(Register::Z), -1),
+            Instruction::Jmp(2),
+            Instruction::Noop,
+        ]);
+
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn should_only_rename_labels_in_macros() {
+        let program: Program = ".macro twice
+x: add x 1
+    jz x x
+.endm
+twice
+twice"
+            .parse()
+            .unwrap();
+
+        let expected = Program(vec![
+            Instruction::Add(Register::X, Operand::Value(1)),
+            Instruction::Jz(Operand::Register(Register::X), -1),
+            Instruction::Add(Register::X, Operand::Value(1)),
+            Instruction::Jz(Operand::Register(Register::X), -1),
+        ]);
+
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn should_report_assembly_errors() {
+        let actual = "noop\n\naddx five".parse::<Program>();
+        let expected = AssemblyError::InvalidInstruction {
+            line: 3,
+            content: "addx five".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = "noop\njmp nowhere".parse::<Program>();
+        let expected = AssemblyError::UnknownLabel {
+            line: 2,
+            label: "nowhere".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = "a:\nnoop\na:".parse::<Program>();
+        let expected = AssemblyError::DuplicateLabel {
+            line: 3,
+            label: "a".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = "5:\nnoop\njmp 5".parse::<Program>();
+        let expected = AssemblyError::InvalidLabel {
+            line: 1,
+            label: "5".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = ".macro skip\n1x: jmp 1x\n.endm\nnoop\nskip".parse::<Program>();
+        let expected = AssemblyError::InvalidLabel {
+            line: 5,
+            label: "1x".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = ".macro inc reg\nadd $reg 1\n.endm\ninc".parse::<Program>();
+        let expected = AssemblyError::MacroArguments {
+            line: 4,
+            name: "inc".to_string(),
+            expected: 1,
+            actual: 0,
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = "noop\n.macro inc reg\nadd $reg 1".parse::<Program>();
+        let expected = AssemblyError::UnterminatedMacro {
+            line: 2,
+            name: "inc".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_disassemble_program() {
+        let program = Program(vec![
+            Instruction::Mov(Register::Y, Operand::Value(3)),
+            Instruction::Add(Register::X, Operand::Value(2)),
+            Instruction::Sub(Register::Y, Operand::Value(1)),
+            Instruction::Jnz(Operand::Register(Register::Y), -2),
+            Instruction::Jz(Operand::Register(Register::X), 2),
+            Instruction::Noop,
+        ]);
+
+        let actual = program.disassemble();
+        let expected = "    mov y 3         ; cycle 1
+l1:
+    add x 2         ; cycles 2-3
+    sub y 1         ; cycles 4-5
+    jnz y l1        ; cycle 6
+    jz x l6         ; cycle 7
+    noop            ; cycle 8
+l6:
+";
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual.parse::<Program>(), Ok(program));
+    }
+
+    #[test]
+    fn should_trace_cycles() {
+        let program: Program = "noop\naddx 3\naddx -5".parse().unwrap();
+        let mut clock_circuit = ClockCircuit::new(program, CrtConfig::default());
+        clock_circuit.enable_trace();
+
+        while clock_circuit.tick().is_ok() {}
+
+        let trace = clock_circuit.trace.unwrap().entries;
+        let x_values: Vec<i64> = trace
+            .iter()
+            .map(|entry| entry.registers.get(Register::X))
+            .collect();
+
+        assert_eq!(x_values, vec![1, 1, 1, 4, 4]);
+        assert_eq!(
+            trace[2],
+            TraceEntry {
+                cycle: 3,
+                program_counter: 1,
+                instruction: Instruction::Add(Register::X, Operand::Value(3)),
+                remaining_cycles: 1,
+                registers: Registers([1, 0, 0, 0]),
+                sprite: (0, 2),
+                pixel: (0, 2),
+                lit: true,
+            }
+        );
+        assert_eq!(
+            trace[2].to_string(),
+            "cycle   3 | pc   1 | add x 3      1 left | x=1 y=0 z=0 w=0 | sprite 0..=2 | pixel (0, 2) #"
+        );
+    }
+
+    #[test]
+    fn should_render_animation_frames() {
+        let program: Program = "noop\naddx 5\nnoop\nnoop".parse().unwrap();
+        let config = CrtConfig {
+            width: 5,
+            height: 1,
+            ..CrtConfig::default()
+        };
+        let mut clock_circuit = ClockCircuit::new(program, config);
+        clock_circuit.enable_trace();
+
+        while clock_circuit.tick().is_ok() {}
+
+        let frames = clock_circuit.animation_frames().unwrap();
+
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[1], [[1, 3, 2, 0, 0]]);
+        assert_eq!(frames[3], [[1, 1, 1, 3, 0]]);
+        assert_eq!(clock_circuit.image(), [[1, 1, 1, 0, 0]]);
+    }
+
+    #[test]
+    fn should_export_images() {
+        let program: Program = "noop\naddx 5\nnoop\nnoop".parse().unwrap();
+        let config = CrtConfig {
+            width: 5,
+            height: 1,
+            ..CrtConfig::default()
+        };
+        let mut clock_circuit = ClockCircuit::new(program, config);
+        clock_circuit.enable_trace();
+
+        while clock_circuit.tick().is_ok() {}
+
+        let mut png_data = Vec::new();
+        clock_circuit.write_png(&mut png_data, 2).unwrap();
+
+        let mut reader = png::Decoder::new(png_data.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (10, 2));
+        assert_eq!(pixels, [1, 1, 1, 1, 1, 1, 0, 0, 0, 0].repeat(2));
+
+        let mut gif_data = Vec::new();
+        clock_circuit.write_gif(&mut gif_data, 2).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(gif_data.as_slice())
+            .unwrap();
+        let mut frame_count = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frame_count += 1;
+        }
+
+        // One frame per cycle and the final image
+        assert_eq!(frame_count, 6);
+    }
+
+    #[test]
+    fn should_reject_invalid_image_sizes() {
+        let program: Program = "noop".parse().unwrap();
+        let mut clock_circuit = ClockCircuit::new(program, CrtConfig::default());
+        clock_circuit.enable_trace();
+
+        while clock_circuit.tick().is_ok() {}
+
+        assert!(matches!(
+            clock_circuit.write_png(Vec::new(), 0),
+            Err(ImageError::InvalidSize { scale: 0, .. })
+        ));
+
+        // 40 pixels scaled by 2000 don't fit into the width of a GIF
+        assert!(matches!(
+            clock_circuit.write_gif(Vec::new(), 2000),
+            Err(ImageError::InvalidSize { scale: 2000, .. })
+        ));
+    }
+
+    #[test]
+    fn should_debug_with_breakpoints() {
+        let program: Program = EXAMPLE_INPUT.parse().unwrap();
+        let mut debugger = Debugger::new(ClockCircuit::new(program, CrtConfig::default()));
+
+        let commands =
+            "break cycle 20\ncontinue\ndelete\nbreak x -1\ncontinue\nstep 2\nquit\nstep\n";
+        let mut output = Vec::new();
+        debugger.repl(commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Hit breakpoint cycle 20\ncycle  20 |"));
+        assert!(output.contains("| x=21 y=0 z=0 w=0 |"));
+        assert!(output.contains("Hit breakpoint x = -1\ncycle 210 |"));
+        assert!(output.contains("\ncycle 212 |"));
+        assert!(!output.contains("cycle 213 |"));
+    }
+
+    #[test]
+    fn should_continue_past_register_breakpoint() {
+        let program: Program = "noop\nnoop\naddx 1\nnoop\naddx -1\nnoop".parse().unwrap();
+        let mut debugger = Debugger::new(ClockCircuit::new(program, CrtConfig::default()));
+
+        let commands = "break x 1\ncontinue\ncontinue\ncontinue\n";
+        let mut output = Vec::new();
+        debugger.repl(commands.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        // Hit at the start and when x changes back, but not while it stays the same
+        assert!(output.contains("Hit breakpoint x = 1\ncycle   1 |"));
+        assert!(output.contains("Hit breakpoint x = 1\ncycle   8 |"));
+        assert!(output.contains("End of program"));
+    }
+
+    #[test]
+    fn should_keep_most_recent_cycles() {
+        let program: Program = "noop\nnoop\nnoop\nnoop".parse().unwrap();
+        let mut clock_circuit = ClockCircuit::new(program, CrtConfig::default());
+        clock_circuit.trace = Some(Trace::new(3));
+
+        while clock_circuit.tick().is_ok() {}
+
+        let trace = clock_circuit.trace.as_ref().unwrap();
+        let cycles: Vec<usize> = trace.entries.iter().map(|entry| entry.cycle).collect();
+
+        assert_eq!(cycles, vec![2, 3, 4]);
+        assert_eq!(trace.dropped, 1);
+        assert!(matches!(
+            clock_circuit.animation_frames(),
+            Err(ImageError::TruncatedTrace)
+        ));
+    }
+
     #[test]
     fn should_calculate_part_1_solution() {
-        let actual = part_1(EXAMPLE_INPUT);
+        let actual = part_1(
+            EXAMPLE_INPUT,
+            CrtConfig::default(),
+            &SignalCycles::default(),
+        );
 
         assert_eq!(actual, 13140);
     }
 
     #[test]
     fn should_calculate_part_2_solution() {
-        let actual = part_2(EXAMPLE_INPUT);
+        // The example doesn't display any letters
+        let actual = part_2(EXAMPLE_INPUT, CrtConfig::default());
 
-        let expected = "##..##..##..##..##..##..##..##..##..##..
+        let expected_image = "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
 #####.....#####.....#####.....#####.....
 ######......######......######......####
 #######.......#######.......#######.....";
 
-        assert_eq!(actual, expected.to_string());
+        let expected = OcrError {
+            unrecognized: (0..8).collect(),
+            image: expected_image.to_string(),
+        };
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_sample_configured_signal_cycles() {
+        assert_eq!(
+            "20+40".parse::<SignalCycles>().unwrap(),
+            SignalCycles::default()
+        );
+
+        let signal_cycles: SignalCycles = "20,60,100,140,180,220".parse().unwrap();
+        let actual = part_1(EXAMPLE_INPUT, CrtConfig::default(), &signal_cycles);
+        assert_eq!(actual, 13140);
+
+        let signal_cycles: SignalCycles = "1+1".parse().unwrap();
+        let actual = part_1(
+            "noop\naddx 3\naddx -5",
+            CrtConfig::default(),
+            &signal_cycles,
+        );
+        assert_eq!(actual, 1 + 2 + 3 + 4 * 4 + 5 * 4);
+
+        assert!("20+0".parse::<SignalCycles>().is_err());
+        assert!("20,x".parse::<SignalCycles>().is_err());
+    }
+
+    #[rstest]
+    #[case(SpriteEdge::Clip, 3, "##......", "#.###...")]
+    #[case(SpriteEdge::Clip, 4, "###.....", "#.####..")]
+    #[case(SpriteEdge::Wrap, 3, "##.....#", "#.###...")]
+    #[case(SpriteEdge::Wrap, 4, "###....#", "#.####..")]
+    fn should_draw_configured_sprite(
+        #[case] sprite_edge: SpriteEdge,
+        #[case] sprite_width: usize,
+        #[case] expected_row_1: &str,
+        #[case] expected_row_2: &str,
+    ) {
+        // The sprite is at column 0 from the second cycle on and moves to column 3 in the second row
+        let program: Program = "mov x 0
+jnz x 100
+jnz x 100
+jnz x 100
+jnz x 100
+jnz x 100
+jnz x 100
+add x 3
+noop
+noop
+noop
+noop
+noop
+noop
+noop"
+            .parse()
+            .unwrap();
+        let config = CrtConfig {
+            width: 8,
+            height: 2,
+            sprite_width,
+            sprite_edge,
+        };
+        let mut clock_circuit = ClockCircuit::new(program, config);
+
+        while clock_circuit.tick().is_ok() {}
+
+        assert_eq!(
+            clock_circuit.to_string(),
+            format!("{expected_row_1}\n{expected_row_2}")
+        );
+    }
+
+    #[test]
+    fn should_read_letters() {
+        let image = [
+            "###..####.#..#.####..##..###..####.####.",
+            "#..#.#....#.#.....#.#..#.#..#.#....#....",
+            "#..#.###..##.....#..#....#..#.###..###..",
+            "###..#....#.#...#...#....###..#....#....",
+            "#.#..#....#.#..#....#..#.#....#....#....",
+            "#..#.####.#..#.####..##..#....####.#....",
+        ];
+
+        let mut clock_circuit = ClockCircuit::new(Program(vec![]), CrtConfig::default());
+        for (row, line) in image.iter().enumerate() {
+            for (col, pixel) in line.chars().enumerate() {
+                clock_circuit.crt[row][col] = pixel == '#';
+            }
+        }
+
+        assert_eq!(clock_circuit.read_letters(), Ok("REKZCPEF".to_string()));
+
+        clock_circuit.crt[0][6] = !clock_circuit.crt[0][6];
+        clock_circuit.crt[2][36] = !clock_circuit.crt[2][36];
+
+        assert!(matches!(
+            clock_circuit.read_letters(),
+            Err(OcrError { unrecognized, .. }) if unrecognized == vec![1, 7]
+        ));
+
+        // A pixel between the letters
+        clock_circuit.crt[0][6] = !clock_circuit.crt[0][6];
+        clock_circuit.crt[2][36] = !clock_circuit.crt[2][36];
+        clock_circuit.crt[3][14] = true;
+
+        assert!(matches!(
+            clock_circuit.read_letters(),
+            Err(OcrError { unrecognized, .. }) if unrecognized == vec![2]
+        ));
     }
 }
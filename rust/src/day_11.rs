@@ -1,17 +1,137 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Display},
+};
+
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while1},
-    combinator::{map, map_res},
-    multi::separated_list0,
-    sequence::{delimited, preceded, separated_pair, tuple},
+    character::complete::{char, digit1, one_of, space0},
+    combinator::{map, map_res, opt},
+    multi::{fold_many0, separated_list0},
+    sequence::{delimited, pair, preceded, tuple},
     IResult,
 };
+use num_bigint::{BigInt, Sign};
+use rayon::prelude::*;
 
-use crate::utils::Day;
+use crate::utils::{has_flag, option_value, Day};
 
 type MonkeyIndex = usize;
 type WorryLevel = u64;
 
+/// A number that can be used as the worry level of an item.
+trait Worry: Clone + Debug + Display + PartialEq + PartialOrd {
+    fn from_level(level: WorryLevel) -> Self;
+
+    /// The least common denominator of the levels, returning `None` if it doesn't fit.
+    fn lcd_of(levels: impl IntoIterator<Item = WorryLevel>) -> Option<Self>;
+
+    /// Add the numbers, returning `None` on overflow.
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+
+    /// Subtract the numbers, returning `None` if the result doesn't fit.
+    fn checked_sub(&self, rhs: &Self) -> Option<Self>;
+
+    /// Multiply the numbers, returning `None` on overflow.
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+
+    /// Divide the numbers, rounding down.
+    fn div_level(&self, rhs: WorryLevel) -> Self;
+
+    /// The remainder of the division, which is never negative.
+    fn rem_level(&self, rhs: WorryLevel) -> WorryLevel;
+
+    /// The remainder of the division by a positive worry level, which is never negative.
+    fn rem_worry(&self, rhs: &Self) -> Self;
+}
+
+impl Worry for WorryLevel {
+    fn from_level(level: WorryLevel) -> Self {
+        level
+    }
+
+    fn lcd_of(levels: impl IntoIterator<Item = WorryLevel>) -> Option<Self> {
+        lcd_many(levels)
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        WorryLevel::checked_add(*self, *rhs)
+    }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        WorryLevel::checked_sub(*self, *rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        WorryLevel::checked_mul(*self, *rhs)
+    }
+
+    fn div_level(&self, rhs: WorryLevel) -> Self {
+        self / rhs
+    }
+
+    fn rem_level(&self, rhs: WorryLevel) -> WorryLevel {
+        self % rhs
+    }
+
+    fn rem_worry(&self, rhs: &Self) -> Self {
+        self % rhs
+    }
+}
+
+/// Arbitrary-precision worry levels, which never overflow and can become negative.
+impl Worry for BigInt {
+    fn from_level(level: WorryLevel) -> Self {
+        BigInt::from(level)
+    }
+
+    fn lcd_of(levels: impl IntoIterator<Item = WorryLevel>) -> Option<Self> {
+        // The greatest common divisor with a level is the one of the level and the remainder
+        Some(levels.into_iter().fold(BigInt::from(1), |lcd, level| {
+            &lcd / gcd(level, lcd.rem_level(level)) * level
+        }))
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        Some(self - rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    fn div_level(&self, rhs: WorryLevel) -> Self {
+        // Division rounds towards zero, so subtract the remainder first
+        (self - self.rem_level(rhs)) / rhs
+    }
+
+    fn rem_level(&self, rhs: WorryLevel) -> WorryLevel {
+        let remainder = self % rhs;
+        let remainder = if remainder.sign() == Sign::Minus {
+            remainder + rhs
+        } else {
+            remainder
+        };
+
+        WorryLevel::try_from(remainder).expect("The remainder is smaller than the divisor")
+    }
+
+    fn rem_worry(&self, rhs: &Self) -> Self {
+        let remainder = self % rhs;
+
+        if remainder.sign() == Sign::Minus {
+            remainder + rhs
+        } else {
+            remainder
+        }
+    }
+}
+
 /// Compute the greatest common divisor of `a` and `b`.
 fn gcd(a: WorryLevel, b: WorryLevel) -> WorryLevel {
     if a == 0 {
@@ -30,17 +150,17 @@ fn gcd(a: WorryLevel, b: WorryLevel) -> WorryLevel {
     a
 }
 
-/// Compute the least common denominator of `a` and `b`.
-fn lcd(a: WorryLevel, b: WorryLevel) -> WorryLevel {
-    (a / gcd(a, b)) * b
+/// Compute the least common denominator of `a` and `b`, returning `None` on overflow.
+fn lcd(a: WorryLevel, b: WorryLevel) -> Option<WorryLevel> {
+    (a / gcd(a, b)).checked_mul(b)
 }
 
-/// Compute the least common denominator of all provided numbers.
-fn lcd_many<I>(levels: I) -> WorryLevel
+/// Compute the least common denominator of all provided numbers, returning `None` on overflow.
+fn lcd_many<I>(levels: I) -> Option<WorryLevel>
 where
     I: IntoIterator<Item = WorryLevel>,
 {
-    levels.into_iter().fold(1, lcd)
+    levels.into_iter().try_fold(1, lcd)
 }
 
 trait SliceExt<T> {
@@ -64,46 +184,111 @@ impl<T> SliceExt<T> for [T] {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Val {
+/// An arithmetic expression that computes the new worry level from the old one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Operation {
     Old,
     Num(WorryLevel),
+    Add(Box<Operation>, Box<Operation>),
+    Sub(Box<Operation>, Box<Operation>),
+    Mul(Box<Operation>, Box<Operation>),
+    Pow(Box<Operation>, u32),
 }
 
-impl Val {
-    fn evaluate(&self, old: WorryLevel) -> WorryLevel {
-        match self {
-            Val::Old => old,
-            Val::Num(num) => *num,
-        }
+impl Operation {
+    /// Compute the new worry level, returning `None` if it doesn't fit.
+    ///
+    /// With a modulus, every intermediate result is capped by it.
+    /// Subtraction then wraps around the modulus instead of becoming negative,
+    /// which gives the same remainder as the exact worry level.
+    fn evaluate<W: Worry>(&self, old: &W, modulus: Option<&W>) -> Option<W> {
+        let cap = |worry: W| match modulus {
+            Some(modulus) => worry.rem_worry(modulus),
+            None => worry,
+        };
+
+        let worry = match self {
+            Operation::Old => old.clone(),
+            Operation::Num(num) => W::from_level(*num),
+            Operation::Add(lhs, rhs) => lhs
+                .evaluate(old, modulus)?
+                .checked_add(&rhs.evaluate(old, modulus)?)?,
+            Operation::Sub(lhs, rhs) => {
+                let lhs = lhs.evaluate(old, modulus)?;
+                let rhs = rhs.evaluate(old, modulus)?;
+
+                match modulus {
+                    Some(modulus) => {
+                        let (lhs, rhs) = (lhs.rem_worry(modulus), rhs.rem_worry(modulus));
+
+                        if lhs >= rhs {
+                            lhs.checked_sub(&rhs)?
+                        } else {
+                            modulus.checked_sub(&rhs.checked_sub(&lhs)?)?
+                        }
+                    }
+                    None => lhs.checked_sub(&rhs)?,
+                }
+            }
+            Operation::Mul(lhs, rhs) => lhs
+                .evaluate(old, modulus)?
+                .checked_mul(&rhs.evaluate(old, modulus)?)?,
+            Operation::Pow(base, exponent) => {
+                let base = base.evaluate(old, modulus)?;
+
+                (0..*exponent).try_fold(W::from_level(1), |power, _| {
+                    power.checked_mul(&base).map(cap)
+                })?
+            }
+        };
+
+        Some(cap(worry))
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Operation {
-    Add(Val, Val),
-    Mul(Val, Val),
+/// The condition of a test on the worry level.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Condition {
+    DivisibleBy(WorryLevel),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
 }
 
-impl Operation {
-    fn evaluate(&self, old: WorryLevel) -> WorryLevel {
+impl Condition {
+    fn is_met<W: Worry>(&self, worry_level: &W) -> bool {
+        match self {
+            Condition::DivisibleBy(divisor) => worry_level.rem_level(*divisor) == 0,
+            Condition::Not(condition) => !condition.is_met(worry_level),
+            Condition::And(lhs, rhs) => lhs.is_met(worry_level) && rhs.is_met(worry_level),
+            Condition::Or(lhs, rhs) => lhs.is_met(worry_level) || rhs.is_met(worry_level),
+        }
+    }
+
+    /// All divisors used in the condition.
+    fn divisors(&self) -> Vec<WorryLevel> {
         match self {
-            Operation::Add(lhs, rhs) => lhs.evaluate(old) + rhs.evaluate(old),
-            Operation::Mul(lhs, rhs) => lhs.evaluate(old) * rhs.evaluate(old),
+            Condition::DivisibleBy(divisor) => vec![*divisor],
+            Condition::Not(condition) => condition.divisors(),
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                let mut divisors = lhs.divisors();
+                divisors.extend(rhs.divisors());
+                divisors
+            }
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 struct Test {
-    divisible_by: WorryLevel,
+    condition: Condition,
     if_true: MonkeyIndex,
     if_false: MonkeyIndex,
 }
 
 impl Test {
-    fn get_next_monkey(&self, worry_level: WorryLevel) -> MonkeyIndex {
-        if worry_level % self.divisible_by == 0 {
+    fn get_next_monkey<W: Worry>(&self, worry_level: &W) -> MonkeyIndex {
+        if self.condition.is_met(worry_level) {
             self.if_true
         } else {
             self.if_false
@@ -112,55 +297,308 @@ impl Test {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-struct Monkey {
-    items: Vec<WorryLevel>,
+struct Monkey<W = WorryLevel> {
+    items: Vec<W>,
     operation: Operation,
     test: Test,
 }
 
-struct MonkeyInTheMiddle {
-    monkeys: Vec<Monkey>,
+/// How the worry levels are kept from growing too much.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorryBound {
+    /// Cap the worry levels by the least common denominator of the tests.
+    Modulo,
+
+    /// Keep the exact worry levels.
+    Exact,
+}
+
+/// A worry level that didn't fit during the inspection of an item.
+///
+/// Worry levels of type `u64` overflow when they become too large or negative.
+#[derive(Debug, PartialEq, Eq)]
+struct OverflowError<W = WorryLevel> {
+    round: usize,
+    monkey: MonkeyIndex,
+
+    /// The position of the item in the list of the monkey.
+    item: usize,
+
+    /// The worry level of the item before the inspection.
+    worry: W,
+}
+
+impl<W: Worry> Display for OverflowError<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Round {}: Monkey {} overflows when inspecting item {} with worry level {}",
+            self.round, self.monkey, self.item, self.worry
+        )
+    }
+}
+
+/// The number of an item, in the order of the starting items.
+type ItemId = usize;
+
+/// A single inspection of an item.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Event<W = WorryLevel> {
+    round: usize,
+    monkey: MonkeyIndex,
+    item: ItemId,
+    old_worry: W,
+
+    /// The worry level after the inspection, with which the item is thrown.
+    new_worry: W,
+    target: MonkeyIndex,
+}
+
+impl<W: Worry> Display for Event<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Round {}: Monkey {} inspects item {} with worry level {} and throws it to monkey {} with worry level {}",
+            self.round, self.monkey, self.item, self.old_worry, self.target, self.new_worry
+        )
+    }
+}
+
+/// Statistics of the inspections of the items, to analyze the game.
+///
+/// The events themselves are not kept, they are passed on while the rounds are played.
+#[derive(Debug)]
+struct EventLog {
+    /// The items held by each monkey, in the same order as their worry levels.
+    item_ids: Vec<Vec<ItemId>>,
+
+    /// The round in which the log was enabled.
+    first_round: usize,
+
+    /// The number of inspections of each monkey, for every round since the log was enabled.
+    round_inspections: Vec<Vec<u64>>,
+
+    /// The monkeys that held each item, starting with the first one.
+    item_paths: BTreeMap<ItemId, Vec<MonkeyIndex>>,
+}
+
+impl EventLog {
+    /// Add an inspection to the statistics.
+    fn record<W>(&mut self, event: &Event<W>) {
+        let round = event.round - self.first_round;
+
+        if self.round_inspections.len() <= round {
+            let monkey_count = self.item_ids.len();
+            self.round_inspections
+                .resize(round + 1, vec![0; monkey_count]);
+        }
+
+        self.round_inspections[round][event.monkey] += 1;
+        self.item_ids[event.target].push(event.item);
+        self.item_paths
+            .entry(event.item)
+            .or_insert_with(|| vec![event.monkey])
+            .push(event.target);
+    }
+
+    /// The number of inspections of each monkey, in buckets of the given number of rounds.
+    fn inspection_histogram(&self, bucket_size: usize) -> Vec<Vec<u64>> {
+        self.round_inspections
+            .chunks(bucket_size)
+            .map(|rounds| {
+                rounds
+                    .iter()
+                    .fold(vec![0; self.item_ids.len()], |mut sum, counts| {
+                        for (total, count) in sum.iter_mut().zip(counts) {
+                            *total += count;
+                        }
+                        sum
+                    })
+            })
+            .collect()
+    }
+}
+
+struct MonkeyInTheMiddle<W = WorryLevel> {
+    monkeys: Vec<Monkey<W>>,
     inspect_count: Vec<u64>,
+
+    /// The number of rounds played so far.
+    round_count: usize,
+
+    /// The inspections during the rounds, if the event log is enabled.
+    event_log: Option<EventLog>,
 }
 
-impl MonkeyInTheMiddle {
-    fn new(monkeys: Vec<Monkey>) -> Self {
+impl<W: Worry> MonkeyInTheMiddle<W> {
+    fn new(monkeys: Vec<Monkey<W>>) -> Self {
         let count = monkeys.len();
 
         Self {
             monkeys,
             inspect_count: (0..count).map(|_| 0).collect(),
+            round_count: 0,
+            event_log: None,
         }
     }
+
+    /// Record all following inspections.
+    ///
+    /// The items are numbered in the order they are currently held.
+    fn enable_event_log(&mut self) {
+        let mut next_id = 0;
+
+        let item_ids = self
+            .monkeys
+            .iter()
+            .map(|monkey| {
+                let ids = (next_id..next_id + monkey.items.len()).collect();
+                next_id += monkey.items.len();
+                ids
+            })
+            .collect();
+
+        self.event_log = Some(EventLog {
+            item_ids,
+            first_round: self.round_count + 1,
+            round_inspections: Vec::new(),
+            item_paths: BTreeMap::new(),
+        });
+    }
+
+    /// The worry levels of the items held by every monkey, like in the puzzle description.
+    fn holdings(&self) -> String {
+        self.monkeys
+            .iter()
+            .enumerate()
+            .map(|(monkey_idx, monkey)| {
+                let items = monkey
+                    .items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!("Monkey {monkey_idx}: {items}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-impl MonkeyInTheMiddle {
+impl MonkeyInTheMiddle<WorryLevel> {
+    /// Convert the game to arbitrary-precision worry levels.
+    fn to_exact(&self) -> MonkeyInTheMiddle<BigInt> {
+        MonkeyInTheMiddle {
+            monkeys: self
+                .monkeys
+                .iter()
+                .map(|monkey| Monkey {
+                    items: monkey
+                        .items
+                        .iter()
+                        .map(|&item| BigInt::from(item))
+                        .collect(),
+                    operation: monkey.operation.clone(),
+                    test: monkey.test.clone(),
+                })
+                .collect(),
+            inspect_count: self.inspect_count.clone(),
+            round_count: self.round_count,
+            event_log: None,
+        }
+    }
+}
+
+impl<W: Worry> MonkeyInTheMiddle<W> {
+    /// The least common denominator of the divisor tests, to cap the worry levels by.
+    ///
+    /// Capping by this value doesn't change the remainders of the tests,
+    /// but dividing a capped worry level by 3 does, so there is no modulus with the worry reduction.
+    /// Returns `None` then, or if the least common denominator does not fit into a worry level.
+    fn worry_modulus(&self, enable_worry_reduction: bool) -> Option<W> {
+        if enable_worry_reduction {
+            return None;
+        }
+
+        W::lcd_of(
+            self.monkeys
+                .iter()
+                .flat_map(|monkey| monkey.test.condition.divisors()),
+        )
+    }
+
+    /// Play a round, capping the worry levels if possible.
     fn round(&mut self, enable_worry_reduction: bool) {
-        // Get the least common denominator of the divisor tests and 3 (for the worry decrease)
-        // This allows us to safely cap by this value without changing the tests
-        let worry_lcd = lcd(
-            lcd_many(self.monkeys.iter().map(|monkey| monkey.test.divisible_by)),
-            3,
-        );
+        self.try_round(enable_worry_reduction, WorryBound::Modulo)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Play a round, capping the worry levels if possible,
+    /// and pass every inspection to `on_event` if the event log is enabled.
+    fn round_with_events(&mut self, enable_worry_reduction: bool, on_event: impl FnMut(&Event<W>)) {
+        self.try_round_with_events(enable_worry_reduction, WorryBound::Modulo, on_event)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Play a round, failing if a worry level overflows.
+    ///
+    /// After an overflow, the state of the game is incomplete.
+    fn try_round(
+        &mut self,
+        enable_worry_reduction: bool,
+        bound: WorryBound,
+    ) -> Result<(), OverflowError<W>> {
+        self.try_round_with_events(enable_worry_reduction, bound, |_| {})
+    }
+
+    /// Play a round like [`Self::try_round`],
+    /// passing every inspection to `on_event` if the event log is enabled.
+    fn try_round_with_events(
+        &mut self,
+        enable_worry_reduction: bool,
+        bound: WorryBound,
+        mut on_event: impl FnMut(&Event<W>),
+    ) -> Result<(), OverflowError<W>> {
+        self.round_count += 1;
+        let modulus = match bound {
+            WorryBound::Modulo => self.worry_modulus(enable_worry_reduction),
+            WorryBound::Exact => None,
+        };
 
         for monkey_idx in 0..self.monkeys.len() {
             let (before, monkey, after) = self.monkeys.split_3_at_mut(monkey_idx);
+            let item_ids = self
+                .event_log
+                .as_mut()
+                .map(|log| std::mem::take(&mut log.item_ids[monkey_idx]))
+                .unwrap_or_default();
 
-            for item in monkey.items.drain(..) {
+            for (item_idx, item) in monkey.items.drain(..).enumerate() {
                 // Monkey inspects item
-                let mut worry = monkey.operation.evaluate(item);
+                let mut worry = monkey
+                    .operation
+                    .evaluate(&item, modulus.as_ref())
+                    .ok_or_else(|| OverflowError {
+                        round: self.round_count,
+                        monkey: monkey_idx,
+                        item: item_idx,
+                        worry: item.clone(),
+                    })?;
                 self.inspect_count[monkey_idx] += 1;
 
                 // Worry level decreases
                 if enable_worry_reduction {
-                    worry /= 3;
+                    worry = worry.div_level(3);
                 }
 
                 // Make sure value doesn't grow too much
-                worry %= worry_lcd;
+                if let Some(modulus) = &modulus {
+                    worry = worry.rem_worry(modulus);
+                }
 
                 // Monkey throws item
-                let next_idx = monkey.test.get_next_monkey(worry);
+                let next_idx = monkey.test.get_next_monkey(&worry);
 
                 let next_monkey = match next_idx.cmp(&monkey_idx) {
                     std::cmp::Ordering::Less => before.get_mut(next_idx).unwrap(),
@@ -170,15 +608,254 @@ impl MonkeyInTheMiddle {
                     }
                 };
 
+                if let Some(log) = &mut self.event_log {
+                    let event = Event {
+                        round: self.round_count,
+                        monkey: monkey_idx,
+                        item: item_ids[item_idx],
+                        old_worry: item.clone(),
+                        new_worry: worry.clone(),
+                        target: next_idx,
+                    };
+
+                    log.record(&event);
+                    on_event(&event);
+                }
+
                 next_monkey.items.push(worry);
             }
         }
+
+        Ok(())
     }
 
-    fn monkey_business_level(&self) -> u64 {
+    /// The product of the two highest inspect counts.
+    ///
+    /// This can exceed `u64` for huge round counts.
+    fn monkey_business_level(&self) -> u128 {
         let mut counts: Vec<u64> = self.inspect_count.clone();
         counts.sort_unstable();
-        counts.iter().rev().take(2).product()
+        counts
+            .iter()
+            .rev()
+            .take(2)
+            .map(|&count| count as u128)
+            .product()
+    }
+}
+
+/// The position of an item at the start of a round: the monkey holding it and its worry level.
+type ItemState = (MonkeyIndex, WorryLevel);
+
+/// The path of a single item through the rounds, until its state repeats.
+#[derive(Debug, PartialEq, Eq)]
+struct ItemTrajectory {
+    /// The distinct states at the start of each round.
+    states: Vec<ItemState>,
+
+    /// The monkeys that inspect the item, for all rounds one after another.
+    inspections: Vec<MonkeyIndex>,
+
+    /// The index of the first inspection of each round, followed by the number of inspections.
+    round_starts: Vec<usize>,
+
+    /// The round whose state is reached again after the last round.
+    cycle_start: usize,
+}
+
+impl ItemTrajectory {
+    /// The index of the stored round that is equivalent to the given round.
+    fn equivalent_round(&self, round: u64) -> usize {
+        let cycle_start = self.cycle_start as u64;
+        let cycle_len = (self.states.len() - self.cycle_start) as u64;
+
+        if round < self.states.len() as u64 {
+            round as usize
+        } else {
+            (cycle_start + (round - cycle_start) % cycle_len) as usize
+        }
+    }
+
+    /// The state of the item after the given number of rounds.
+    fn state_after(&self, rounds: u64) -> ItemState {
+        self.states[self.equivalent_round(rounds)]
+    }
+
+    /// Add the inspections of the item during the given number of rounds to the counts.
+    fn count_inspections(&self, rounds: u64, inspect_count: &mut [u64]) {
+        let mut add_rounds = |range: std::ops::Range<usize>, factor: u64| {
+            let inspections =
+                &self.inspections[self.round_starts[range.start]..self.round_starts[range.end]];
+
+            for monkey_idx in inspections {
+                inspect_count[*monkey_idx] += factor;
+            }
+        };
+
+        let cycle_start = self.cycle_start as u64;
+        let cycle_len = (self.states.len() - self.cycle_start) as u64;
+
+        if rounds <= self.states.len() as u64 {
+            add_rounds(0..rounds as usize, 1);
+        } else {
+            let full_cycles = (rounds - cycle_start) / cycle_len;
+            let remainder = ((rounds - cycle_start) % cycle_len) as usize;
+
+            add_rounds(0..self.cycle_start, 1);
+            add_rounds(self.cycle_start..self.states.len(), full_cycles);
+            add_rounds(self.cycle_start..self.cycle_start + remainder, 1);
+        }
+    }
+}
+
+impl MonkeyInTheMiddle<WorryLevel> {
+    /// Play a single round for a single item.
+    ///
+    /// Returns the state after the round and adds the monkeys that inspected the item
+    /// to `inspections`, or `None` if the worry level overflows.
+    fn item_round(
+        &self,
+        (mut monkey_idx, mut worry): ItemState,
+        modulus: WorryLevel,
+        inspections: &mut Vec<MonkeyIndex>,
+    ) -> Option<ItemState> {
+        loop {
+            let monkey = &self.monkeys[monkey_idx];
+
+            worry = monkey.operation.evaluate(&worry, Some(&modulus))?;
+            inspections.push(monkey_idx);
+
+            let next_idx = monkey.test.get_next_monkey(&worry);
+
+            // Monkeys that already had their turn get the item in the next round
+            if next_idx <= monkey_idx {
+                return Some((next_idx, worry));
+            }
+
+            monkey_idx = next_idx;
+        }
+    }
+
+    /// Follow the item through the rounds until its state repeats.
+    fn item_trajectory(&self, start: ItemState, modulus: WorryLevel) -> ItemTrajectory {
+        let mut seen: HashMap<ItemState, usize> = HashMap::new();
+        let mut states = Vec::new();
+        let mut inspections = Vec::new();
+        let mut round_starts = Vec::new();
+        let mut state = start;
+
+        let cycle_start = loop {
+            round_starts.push(inspections.len());
+
+            if let Some(&round) = seen.get(&state) {
+                break round;
+            }
+
+            seen.insert(state, states.len());
+            states.push(state);
+
+            state = self
+                .item_round(state, modulus, &mut inspections)
+                .expect("The capped worry levels should not overflow");
+        };
+
+        ItemTrajectory {
+            states,
+            inspections,
+            round_starts,
+            cycle_start,
+        }
+    }
+
+    /// Take the items from all monkeys with capped worry levels, to follow every item on its own.
+    ///
+    /// The order of the items held by a monkey can change that way, so the event log is disabled.
+    fn take_items(&mut self, modulus: WorryLevel) -> Vec<ItemState> {
+        self.event_log = None;
+
+        self.monkeys
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(monkey_idx, monkey)| {
+                monkey
+                    .items
+                    .drain(..)
+                    .map(move |worry| (monkey_idx, worry % modulus))
+            })
+            .collect()
+    }
+
+    /// Skip the given number of rounds with the rules of part 2.
+    ///
+    /// Dividing the worry levels by 3 doesn't keep their remainders,
+    /// so the rounds with the worry reduction cannot be skipped.
+    /// Every item is followed on its own until its state repeats,
+    /// so that the time does not depend on the number of rounds.
+    /// The order of the items held by a monkey can differ from playing the rounds,
+    /// so the event log is disabled.
+    ///
+    /// # Panics
+    ///
+    /// If the worry levels cannot be capped, the states of the items never repeat.
+    /// The capped worry levels must also not overflow.
+    fn fast_forward(&mut self, rounds: u64) {
+        let modulus = self
+            .worry_modulus(false)
+            .expect("Fast-forwarding needs capped worry levels");
+        let items = self.take_items(modulus);
+
+        for item in items {
+            let trajectory = self.item_trajectory(item, modulus);
+            trajectory.count_inspections(rounds, &mut self.inspect_count);
+
+            let (monkey_idx, worry) = trajectory.state_after(rounds);
+            self.monkeys[monkey_idx].items.push(worry);
+        }
+
+        self.round_count += rounds as usize;
+    }
+
+    /// Play the given number of rounds with the rules of part 2,
+    /// following every item on its own in parallel.
+    ///
+    /// This gives the same inspect counts as playing the rounds one after another,
+    /// but the order of the items held by a monkey can differ, so the event log is disabled.
+    ///
+    /// Returns `None` if the worry levels cannot be capped or overflow.
+    /// After an overflow, the state of the game is incomplete.
+    fn play_parallel(&mut self, rounds: usize) -> Option<()> {
+        let modulus = self.worry_modulus(false)?;
+        let items = self.take_items(modulus);
+
+        let monkey_count = self.monkeys.len();
+        let journeys: Vec<(ItemState, Vec<u64>)> = items
+            .into_par_iter()
+            .map(|mut state| {
+                let mut inspect_count = vec![0; monkey_count];
+                let mut inspections = Vec::new();
+
+                for _ in 0..rounds {
+                    state = self.item_round(state, modulus, &mut inspections)?;
+
+                    for monkey_idx in inspections.drain(..) {
+                        inspect_count[monkey_idx] += 1;
+                    }
+                }
+
+                Some((state, inspect_count))
+            })
+            .collect::<Option<_>>()?;
+
+        for ((monkey_idx, worry), inspect_count) in journeys {
+            self.monkeys[monkey_idx].items.push(worry);
+
+            for (total, count) in self.inspect_count.iter_mut().zip(inspect_count) {
+                *total += count;
+            }
+        }
+
+        self.round_count += rounds;
+        Some(())
     }
 }
 
@@ -199,24 +876,100 @@ fn parse_starting_items(input: &str) -> IResult<&str, Vec<WorryLevel>> {
     )(input)
 }
 
-fn parse_val(input: &str) -> IResult<&str, Val> {
-    let parse_old = map(tag("old"), |_| Val::Old);
-    let parse_num = map(parse_worry_level, Val::Num);
+/// Parse a value or an expression in parentheses.
+fn parse_atom(input: &str) -> IResult<&str, Operation> {
+    let parse_old = map(tag("old"), |_| Operation::Old);
+    let parse_num = map(parse_worry_level, Operation::Num);
+    let parse_parens = delimited(
+        pair(char('('), space0),
+        parse_expression,
+        pair(space0, char(')')),
+    );
+
+    alt((parse_old, parse_num, parse_parens))(input)
+}
+
+/// Parse a power with a constant exponent, like `old ^ 2`.
+fn parse_power(input: &str) -> IResult<&str, Operation> {
+    let (input, base) = parse_atom(input)?;
+    let (input, exponent) = opt(preceded(
+        tuple((space0, char('^'), space0)),
+        map_res(digit1, |num: &str| num.parse::<u32>()),
+    ))(input)?;
+
+    let power = match exponent {
+        Some(exponent) => Operation::Pow(Box::new(base), exponent),
+        None => base,
+    };
+
+    Ok((input, power))
+}
+
+fn parse_product(input: &str) -> IResult<&str, Operation> {
+    let (input, first) = parse_power(input)?;
+
+    fold_many0(
+        preceded(tuple((space0, char('*'), space0)), parse_power),
+        move || first.clone(),
+        |lhs, rhs| Operation::Mul(Box::new(lhs), Box::new(rhs)),
+    )(input)
+}
+
+/// Parse an arithmetic expression with `+`, `-`, `*`, `^` and parentheses.
+fn parse_expression(input: &str) -> IResult<&str, Operation> {
+    let (input, first) = parse_product(input)?;
 
-    alt((parse_old, parse_num))(input)
+    fold_many0(
+        pair(delimited(space0, one_of("+-"), space0), parse_product),
+        move || first.clone(),
+        |lhs, (operator, rhs)| match operator {
+            '+' => Operation::Add(Box::new(lhs), Box::new(rhs)),
+            _ => Operation::Sub(Box::new(lhs), Box::new(rhs)),
+        },
+    )(input)
 }
 
 fn parse_operation(input: &str) -> IResult<&str, Operation> {
-    let parse_add = map(
-        separated_pair(parse_val, tag(" + "), parse_val),
-        |(lhs, rhs)| Operation::Add(lhs, rhs),
+    preceded(tag("Operation: new = "), parse_expression)(input)
+}
+
+/// Parse a single condition, a negated condition or conditions in parentheses.
+fn parse_condition_atom(input: &str) -> IResult<&str, Condition> {
+    let parse_divisible_by = map(
+        preceded(tag("divisible by "), parse_worry_level),
+        Condition::DivisibleBy,
     );
-    let parse_mul = map(
-        separated_pair(parse_val, tag(" * "), parse_val),
-        |(lhs, rhs)| Operation::Mul(lhs, rhs),
+    let parse_not = map(preceded(tag("not "), parse_condition_atom), |condition| {
+        Condition::Not(Box::new(condition))
+    });
+    let parse_parens = delimited(
+        pair(char('('), space0),
+        parse_condition,
+        pair(space0, char(')')),
     );
 
-    preceded(tag("Operation: new = "), alt((parse_add, parse_mul)))(input)
+    alt((parse_divisible_by, parse_not, parse_parens))(input)
+}
+
+fn parse_conjunction(input: &str) -> IResult<&str, Condition> {
+    let (input, first) = parse_condition_atom(input)?;
+
+    fold_many0(
+        preceded(tag(" and "), parse_condition_atom),
+        move || first.clone(),
+        |lhs, rhs| Condition::And(Box::new(lhs), Box::new(rhs)),
+    )(input)
+}
+
+/// Parse conditions combined with `not`, `and`, `or` and parentheses.
+fn parse_condition(input: &str) -> IResult<&str, Condition> {
+    let (input, first) = parse_conjunction(input)?;
+
+    fold_many0(
+        preceded(tag(" or "), parse_conjunction),
+        move || first.clone(),
+        |lhs, rhs| Condition::Or(Box::new(lhs), Box::new(rhs)),
+    )(input)
 }
 
 fn parse_monkey_index(input: &str) -> IResult<&str, MonkeyIndex> {
@@ -230,20 +983,14 @@ fn parse_throw_to(input: &str) -> IResult<&str, MonkeyIndex> {
 }
 
 fn parse_test(input: &str) -> IResult<&str, Test> {
-    let parse_divisible_by = preceded(tag("Test: divisible by "), parse_worry_level);
+    let parse_condition = preceded(tag("Test: "), parse_condition);
     let parse_true = preceded(tag("If true: "), parse_throw_to);
     let parse_false = preceded(tag("If false: "), parse_throw_to);
 
     map(
-        tuple((
-            parse_divisible_by,
-            parse_ws,
-            parse_true,
-            parse_ws,
-            parse_false,
-        )),
-        |(divisible_by, _, if_true, _, if_false)| Test {
-            divisible_by,
+        tuple((parse_condition, parse_ws, parse_true, parse_ws, parse_false)),
+        |(condition, _, if_true, _, if_false)| Test {
+            condition,
             if_true,
             if_false,
         },
@@ -287,33 +1034,257 @@ impl Day for Day11 {
     fn run(&self) {
         let input = self.get_input();
 
-        println!("Part 1: {}", part_1(&input));
-        println!("Part 2: {}", part_2(&input));
+        if has_flag("--checked") {
+            match find_overflow(&input, 10000) {
+                Some(err) => println!("Checked: {err}"),
+                None => println!("Checked: No overflow in 10000 rounds"),
+            }
+        }
+
+        if let Some(rounds) = option_value("--exact") {
+            let rounds = rounds.parse().expect("Invalid number of rounds");
+
+            match validate_worry_bound(&input, rounds) {
+                Some(round) => println!("Exact: The capped worry levels differ in round {round}"),
+                None => println!("Exact: The capped worry levels match for {rounds} rounds"),
+            }
+        }
+
+        if let Some(rounds) = option_value("--rounds") {
+            let rounds = rounds.parse().expect("Invalid number of rounds");
+            let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(&input).unwrap();
+            monkey_in_the_middle.fast_forward(rounds);
+
+            println!(
+                "After {rounds} rounds: {}",
+                monkey_in_the_middle.monkey_business_level()
+            );
+        }
+
+        let print_rounds: Vec<usize> = option_value("--print-rounds")
+            .map(|rounds| {
+                rounds
+                    .split(',')
+                    .map(|round| round.trim().parse().expect("Invalid round"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if has_flag("--events") || has_flag("--report") || !print_rounds.is_empty() {
+            // The rules of the given part, playing at least until the last round to print
+            let (rounds, enable_worry_reduction) = match option_value("--part").as_deref() {
+                None | Some("1") => (20, true),
+                Some("2") => (10000, false),
+                Some(part) => panic!("Invalid part: {part}"),
+            };
+            let rounds = print_rounds.iter().copied().fold(rounds, usize::max);
+
+            print_report(
+                &input,
+                rounds,
+                enable_worry_reduction,
+                &print_rounds,
+                has_flag("--events"),
+                has_flag("--report"),
+            );
+        }
+
+        // Only solve the given part, to time the engines on their own
+        let part = option_value("--part");
+        let parallel = has_flag("--parallel");
+
+        if part.as_deref() != Some("2") {
+            let answer = if parallel {
+                part_1_parallel(&input)
+            } else {
+                part_1(&input)
+            };
+            println!("Part 1: {answer}");
+        }
+        if part.as_deref() != Some("1") {
+            let answer = if parallel {
+                part_2_parallel(&input)
+            } else {
+                part_2(&input)
+            };
+            println!("Part 2: {answer}");
+        }
     }
 }
 
-fn part_1(input: &str) -> WorryLevel {
+/// The number of buckets of the inspection histogram.
+const HISTOGRAM_BUCKETS: usize = 4;
+
+/// Play the given number of rounds with the event log and print the events as they happen,
+/// the worry levels after the given rounds, and the inspection histogram and item paths.
+fn print_report(
+    input: &str,
+    rounds: usize,
+    enable_worry_reduction: bool,
+    print_rounds: &[usize],
+    print_events: bool,
+    print_stats: bool,
+) {
     let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+    monkey_in_the_middle.enable_event_log();
 
-    for _ in 0..20 {
-        monkey_in_the_middle.round(true);
+    for round in 1..=rounds {
+        monkey_in_the_middle.round_with_events(enable_worry_reduction, |event| {
+            if print_events {
+                println!("{event}");
+            }
+        });
+
+        if print_rounds.contains(&round) {
+            println!(
+                "After round {round}, the monkeys are holding items with these worry levels:\n{}\n",
+                monkey_in_the_middle.holdings()
+            );
+        }
     }
 
-    monkey_in_the_middle.monkey_business_level()
+    let log = monkey_in_the_middle
+        .event_log
+        .expect("The event log is enabled");
+
+    if print_stats {
+        let bucket_size = (rounds / HISTOGRAM_BUCKETS).max(1);
+        println!("Inspections per {bucket_size} rounds:");
+
+        for (bucket, counts) in log.inspection_histogram(bucket_size).iter().enumerate() {
+            let first_round = bucket * bucket_size + 1;
+            let last_round = (first_round + bucket_size - 1).min(rounds);
+            let rounds = format!("{first_round}-{last_round}");
+            let counts = counts
+                .iter()
+                .enumerate()
+                .map(|(monkey_idx, count)| format!("Monkey {monkey_idx}: {count:>4}"))
+                .collect::<Vec<_>>()
+                .join(" | ");
+
+            println!("Rounds {rounds:<7} {counts}");
+        }
+        println!();
+
+        println!("Item paths:");
+        for (item, path) in log.item_paths.iter() {
+            let path = path
+                .iter()
+                .map(|monkey_idx| monkey_idx.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            println!("Item {item}: {path}");
+        }
+        println!();
+    }
 }
 
-fn part_2(input: &str) -> WorryLevel {
+/// Play the rounds of part 2 without capping the worry levels and find the first overflow.
+fn find_overflow(input: &str, rounds: usize) -> Option<OverflowError> {
     let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
 
-    for _ in 0..10000 {
-        monkey_in_the_middle.round(false);
+    (0..rounds).find_map(|_| {
+        monkey_in_the_middle
+            .try_round(false, WorryBound::Exact)
+            .err()
+    })
+}
+
+/// Play the rounds of part 2 with capped worry levels and with exact worry levels.
+///
+/// Returns the first round in which they lead to a different state, if any.
+fn validate_worry_bound(input: &str, rounds: usize) -> Option<usize> {
+    let (_, mut capped) = parse_monkey_in_the_middle(input).unwrap();
+    let mut exact = capped.to_exact();
+    let modulus = capped
+        .worry_modulus(false)
+        .expect("The worry levels cannot be capped");
+
+    (1..=rounds).find(|_| {
+        capped.round(false);
+        exact
+            .try_round(false, WorryBound::Exact)
+            .expect("Exact worry levels cannot overflow");
+
+        let same_items = capped
+            .monkeys
+            .iter()
+            .zip(exact.monkeys.iter())
+            .all(|(a, b)| {
+                a.items.len() == b.items.len()
+                    && a.items
+                        .iter()
+                        .zip(b.items.iter())
+                        .all(|(a, b)| *a == b.rem_level(modulus))
+            });
+
+        !same_items || capped.inspect_count != exact.inspect_count
+    })
+}
+
+/// Play the rounds and compute the monkey business level.
+///
+/// If the worry levels overflow, the game is played again with arbitrary-precision
+/// worry levels, which are still capped without the worry reduction,
+/// even if the least common denominator of the tests doesn't fit into a `u64`.
+fn play(input: &str, rounds: usize, enable_worry_reduction: bool) -> u128 {
+    let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+    let mut exact = monkey_in_the_middle.to_exact();
+
+    let played = (0..rounds).try_for_each(|_| {
+        monkey_in_the_middle.try_round(enable_worry_reduction, WorryBound::Modulo)
+    });
+
+    if played.is_ok() {
+        return monkey_in_the_middle.monkey_business_level();
+    }
+
+    for _ in 0..rounds {
+        exact.round(enable_worry_reduction);
+    }
+
+    exact.monkey_business_level()
+}
+
+fn part_1(input: &str) -> u128 {
+    play(input, 20, true)
+}
+
+fn part_2(input: &str) -> u128 {
+    play(input, 10000, false)
+}
+
+/// Play the rounds in parallel and compute the monkey business level.
+///
+/// Dividing by 3 doesn't keep the remainders of the capped worry levels, so the rounds
+/// with the worry reduction are played one after another,
+/// like when the worry levels overflow.
+fn play_in_parallel(input: &str, rounds: usize, enable_worry_reduction: bool) -> u128 {
+    if enable_worry_reduction {
+        return play(input, rounds, enable_worry_reduction);
+    }
+
+    let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+
+    match monkey_in_the_middle.play_parallel(rounds) {
+        Some(()) => monkey_in_the_middle.monkey_business_level(),
+        None => play(input, rounds, enable_worry_reduction),
     }
+}
 
-    monkey_in_the_middle.monkey_business_level()
+fn part_1_parallel(input: &str) -> u128 {
+    play_in_parallel(input, 20, true)
+}
+
+fn part_2_parallel(input: &str) -> u128 {
+    play_in_parallel(input, 10000, false)
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
     const EXAMPLE_INPUT: &'static str = "Monkey 0:
@@ -352,7 +1323,21 @@ mod tests {
 
     #[test]
     fn should_calculate_lcd() {
-        assert_eq!(lcd(12, 18), 36);
+        assert_eq!(lcd(12, 18), Some(36));
+        assert_eq!(lcd(1 << 40, 3 << 30), Some(3 << 40));
+        assert_eq!(lcd(1 << 40, 3 << 40), Some(3 << 40));
+        assert_eq!(lcd(u64::MAX, 2), None);
+    }
+
+    #[test]
+    fn should_calculate_lcd_of_big_integers() {
+        let levels = [4294967311, 4, 4294967357, 6];
+
+        assert_eq!(WorryLevel::lcd_of(levels), None);
+        assert_eq!(
+            BigInt::lcd_of(levels),
+            Some(BigInt::from(4294967311u64) * 4294967357u64 * 12)
+        );
     }
 
     #[test]
@@ -402,9 +1387,9 @@ mod tests {
 
         let expected = Monkey {
             items: vec![79, 98],
-            operation: Operation::Mul(Val::Old, Val::Num(19)),
+            operation: Operation::Mul(Box::new(Operation::Old), Box::new(Operation::Num(19))),
             test: Test {
-                divisible_by: 23,
+                condition: Condition::DivisibleBy(23),
                 if_true: 2,
                 if_false: 3,
             },
@@ -415,6 +1400,240 @@ mod tests {
         assert_eq!(actual, Ok(("", expected)));
     }
 
+    #[rstest]
+    #[case("old * 19", 10, Some(190))]
+    #[case("3 + old * old", 10, Some(103))]
+    #[case("(3 + old) * old", 10, Some(130))]
+    #[case("old - 2 - 3", 10, Some(5))]
+    #[case("2 * (old - 1) ^ 3", 4, Some(54))]
+    #[case("old - 11", 10, None)]
+    #[case("old ^ 30", 10, None)]
+    fn should_evaluate_operation(
+        #[case] expression: &str,
+        #[case] old: WorryLevel,
+        #[case] expected: Option<WorryLevel>,
+    ) {
+        let (rest, operation) = parse_expression(expression).unwrap();
+
+        assert_eq!(rest, "");
+        assert_eq!(operation.evaluate(&old, None), expected);
+    }
+
+    #[test]
+    fn should_evaluate_operation_with_modulus() {
+        let (_, operation) = parse_expression("old ^ 30 + 1").unwrap();
+        let expected = (BigInt::from(10).pow(30) + 1) % 7;
+
+        assert_eq!(
+            operation.evaluate(&10, Some(&7)),
+            Some(WorryLevel::try_from(expected).unwrap())
+        );
+
+        // -1 wraps around to 6
+        let (_, operation) = parse_expression("old - 11").unwrap();
+        assert_eq!(operation.evaluate(&10, Some(&7)), Some(6));
+    }
+
+    #[test]
+    fn should_evaluate_negative_exact_worry_levels() {
+        let (_, operation) = parse_expression("old - 11").unwrap();
+        let worry = operation.evaluate(&BigInt::from(10), None).unwrap();
+
+        assert_eq!(worry, BigInt::from(-1));
+        assert_eq!(worry.rem_level(7), 6);
+        assert_eq!(worry.div_level(3), BigInt::from(-1));
+        assert_eq!(BigInt::from(-7).div_level(3), BigInt::from(-3));
+    }
+
+    #[test]
+    fn should_parse_compound_test() {
+        let (rest, condition) =
+            parse_condition("divisible by 2 and not divisible by 3 or (divisible by 5)").unwrap();
+        let expected = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(Condition::DivisibleBy(2)),
+                Box::new(Condition::Not(Box::new(Condition::DivisibleBy(3)))),
+            )),
+            Box::new(Condition::DivisibleBy(5)),
+        );
+
+        assert_eq!(rest, "");
+        assert_eq!(condition, expected);
+        assert_eq!(condition.divisors(), vec![2, 3, 5]);
+
+        let met: Vec<WorryLevel> = (1..=10).filter(|worry| condition.is_met(worry)).collect();
+        assert_eq!(met, vec![2, 4, 5, 8, 10]);
+    }
+
+    #[test]
+    fn should_only_cap_worry_levels_if_valid() {
+        let (_, monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        assert_eq!(
+            monkey_in_the_middle.worry_modulus(false),
+            Some(23 * 19 * 13 * 17)
+        );
+        assert_eq!(monkey_in_the_middle.worry_modulus(true), None);
+
+        let input = "Monkey 0:
+            Starting items: 10
+            Operation: new = old * 2 - 1
+            Test: divisible by 4294967311 or divisible by 4
+              If true: throw to monkey 1
+              If false: throw to monkey 1
+
+        Monkey 1:
+            Starting items: 7
+            Operation: new = old + 5
+            Test: not divisible by 4294967357
+              If true: throw to monkey 0
+              If false: throw to monkey 0";
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+
+        // The product of the two primes does not fit into a worry level
+        assert_eq!(monkey_in_the_middle.worry_modulus(false), None);
+
+        monkey_in_the_middle.round(false);
+
+        // 10 * 2 - 1 = 19 goes to monkey 1, then 7 + 5 = 12 and 19 + 5 = 24 go to monkey 0
+        assert_eq!(monkey_in_the_middle.monkeys[0].items, vec![12, 24]);
+        assert_eq!(monkey_in_the_middle.inspect_count, vec![1, 2]);
+    }
+
+    #[test]
+    fn should_play_with_exact_worry_levels() {
+        let (_, monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        let mut exact = monkey_in_the_middle.to_exact();
+
+        for _ in 0..20 {
+            exact.try_round(true, WorryBound::Exact).unwrap();
+        }
+
+        assert_eq!(exact.monkey_business_level(), 10605);
+        assert_eq!(validate_worry_bound(EXAMPLE_INPUT, 20), None);
+    }
+
+    #[test]
+    fn should_find_overflow() {
+        let actual = find_overflow(EXAMPLE_INPUT, 20);
+        // Monkey 0 multiplies by 19
+        let expected = OverflowError {
+            round: 13,
+            monkey: 0,
+            item: 7,
+            worry: 13988703546165100909,
+        };
+
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[rstest]
+    #[case(EXAMPLE_INPUT, 1)]
+    #[case(EXAMPLE_INPUT, 12)]
+    #[case(&EXAMPLE_INPUT.replace("79, 98", "1000000079, 98"), 1)]
+    #[case(&EXAMPLE_INPUT.replace("79, 98", "1000000079, 98"), 12)]
+    fn should_fast_forward_like_exact_worry_levels(#[case] input: &str, #[case] rounds: u64) {
+        let (_, mut fast_forwarded) = parse_monkey_in_the_middle(input).unwrap();
+        let mut exact = fast_forwarded.to_exact();
+        let modulus = fast_forwarded.worry_modulus(false).unwrap();
+
+        for _ in 0..rounds {
+            exact.try_round(false, WorryBound::Exact).unwrap();
+        }
+        fast_forwarded.fast_forward(rounds);
+
+        assert_eq!(fast_forwarded.inspect_count, exact.inspect_count);
+
+        for (fast_forwarded, exact) in fast_forwarded.monkeys.iter().zip(exact.monkeys.iter()) {
+            let mut fast_forwarded_items = fast_forwarded.items.clone();
+            let mut exact_items: Vec<_> = exact
+                .items
+                .iter()
+                .map(|item| item.rem_level(modulus))
+                .collect();
+            fast_forwarded_items.sort_unstable();
+            exact_items.sort_unstable();
+
+            assert_eq!(fast_forwarded_items, exact_items);
+        }
+    }
+
+    #[test]
+    fn should_fast_forward_part_2() {
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        monkey_in_the_middle.fast_forward(10000);
+
+        assert_eq!(monkey_in_the_middle.monkey_business_level(), 2713310158);
+    }
+
+    #[test]
+    fn should_fast_forward_many_rounds() {
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        monkey_in_the_middle.fast_forward(1_000_000_000_000);
+
+        let item_count: u64 = monkey_in_the_middle
+            .monkeys
+            .iter()
+            .map(|monkey| monkey.items.len() as u64)
+            .sum();
+        let inspect_count: u64 = monkey_in_the_middle.inspect_count.iter().sum();
+
+        assert_eq!(item_count, 10);
+        assert!(inspect_count > 10 * 1_000_000_000_000);
+    }
+
+    #[test]
+    fn should_print_holdings_like_puzzle() {
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        monkey_in_the_middle.round(true);
+
+        let expected = "Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2: 
+Monkey 3: ";
+
+        assert_eq!(monkey_in_the_middle.holdings(), expected);
+    }
+
+    #[test]
+    fn should_log_events() {
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(EXAMPLE_INPUT).unwrap();
+        monkey_in_the_middle.enable_event_log();
+
+        let mut first_event = None;
+        for _ in 0..20 {
+            monkey_in_the_middle.round_with_events(true, |event| {
+                first_event.get_or_insert_with(|| event.clone());
+            });
+        }
+
+        let log = monkey_in_the_middle.event_log.unwrap();
+
+        assert_eq!(
+            first_event.unwrap(),
+            Event {
+                round: 1,
+                monkey: 0,
+                item: 0,
+                old_worry: 79,
+                new_worry: 500,
+                target: 3,
+            }
+        );
+        assert_eq!(log.inspection_histogram(20), vec![vec![101, 95, 7, 105]]);
+        assert_eq!(log.inspection_histogram(1)[0], vec![2, 4, 3, 5]);
+
+        let paths = &log.item_paths;
+        assert_eq!(paths.len(), 10);
+        assert_eq!(paths[&0][..3], [0, 3, 1]);
+
+        // The ids follow the items to the monkeys holding them
+        for (monkey_idx, item_ids) in log.item_ids.iter().enumerate() {
+            for item_id in item_ids {
+                assert_eq!(paths[item_id].last(), Some(&monkey_idx));
+            }
+        }
+    }
+
     #[test]
     fn should_calculate_part_1_solution() {
         let actual = part_1(EXAMPLE_INPUT);
@@ -428,4 +1647,149 @@ mod tests {
 
         assert_eq!(actual, 2713310158);
     }
+
+    #[test]
+    fn should_cap_worry_levels_with_subtraction() {
+        let input = "Monkey 0:
+            Starting items: 10, 20
+            Operation: new = old * 5 - 2
+            Test: divisible by 7
+              If true: throw to monkey 1
+              If false: throw to monkey 2
+
+        Monkey 1:
+            Starting items: 3
+            Operation: new = old * old - old
+            Test: divisible by 5
+              If true: throw to monkey 2
+              If false: throw to monkey 0
+
+        Monkey 2:
+            Starting items: 4
+            Operation: new = old + 3
+            Test: divisible by 11
+              If true: throw to monkey 0
+              If false: throw to monkey 1";
+
+        assert_eq!(validate_worry_bound(input, 8), None);
+        assert_eq!(part_2(input), 599999994);
+
+        let (_, mut monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+        monkey_in_the_middle.fast_forward(10000);
+        assert_eq!(monkey_in_the_middle.monkey_business_level(), 599999994);
+    }
+
+    #[test]
+    fn should_not_cap_worry_levels_with_worry_reduction() {
+        let input = EXAMPLE_INPUT
+            .replace("by 23", "by 2")
+            .replace("by 19", "by 3")
+            .replace("by 13", "by 5")
+            .replace("by 17", "by 7");
+        let (_, monkey_in_the_middle) = parse_monkey_in_the_middle(&input).unwrap();
+        let mut exact = monkey_in_the_middle.to_exact();
+
+        for _ in 0..20 {
+            exact.try_round(true, WorryBound::Exact).unwrap();
+        }
+
+        // Capping by 2 * 3 * 5 * 7 before dividing by 3 would give 9555
+        assert_eq!(exact.monkey_business_level(), 11400);
+        assert_eq!(part_1(&input), 11400);
+        assert_eq!(part_1_parallel(&input), 11400);
+    }
+
+    #[test]
+    fn should_allow_negative_worry_levels() {
+        let input = "Monkey 0:
+            Starting items: 2, 9
+            Operation: new = old - 5
+            Test: divisible by 3
+              If true: throw to monkey 1
+              If false: throw to monkey 1
+
+        Monkey 1:
+            Starting items: 4
+            Operation: new = old * 2 - 7
+            Test: divisible by 2
+              If true: throw to monkey 0
+              If false: throw to monkey 0";
+
+        // Without capping, the worry levels don't fit into a `u64` once they become negative
+        assert!(find_overflow(input, 1).is_some());
+        assert_eq!(validate_worry_bound(input, 20), None);
+
+        for enable_worry_reduction in [true, false] {
+            let (_, monkey_in_the_middle) = parse_monkey_in_the_middle(input).unwrap();
+            let mut exact = monkey_in_the_middle.to_exact();
+
+            for _ in 0..20 {
+                exact
+                    .try_round(enable_worry_reduction, WorryBound::Exact)
+                    .unwrap();
+            }
+
+            assert_eq!(
+                play(input, 20, enable_worry_reduction),
+                exact.monkey_business_level()
+            );
+        }
+    }
+
+    #[test]
+    fn should_fall_back_to_exact_worry_levels() {
+        let input = "Monkey 0:
+            Starting items: 4294967300
+            Operation: new = old * old
+            Test: divisible by 4294967311
+              If true: throw to monkey 1
+              If false: throw to monkey 1
+
+        Monkey 1:
+            Starting items: 1
+            Operation: new = old + 1
+            Test: divisible by 2
+              If true: throw to monkey 0
+              If false: throw to monkey 0";
+
+        // The square of the worry level overflows, even when it is capped
+        assert!(find_overflow(input, 1).is_some());
+        assert_eq!(play(input, 3, false), 5 * 6);
+        assert_eq!(play_in_parallel(input, 3, false), 5 * 6);
+    }
+
+    #[test]
+    fn should_cap_exact_worry_levels_with_big_modulus() {
+        let input = "Monkey 0:
+            Starting items: 2
+            Operation: new = old * old
+            Test: divisible by 4294967311
+              If true: throw to monkey 1
+              If false: throw to monkey 1
+
+        Monkey 1:
+            Starting items: 3
+            Operation: new = old + 1
+            Test: divisible by 4294967357
+              If true: throw to monkey 0
+              If false: throw to monkey 0";
+
+        // The product of the two primes does not fit into a `u64`, and the squares would grow
+        // without bound, but the big integers are capped
+        assert_eq!(play(input, 10000, false), 19999 * 20000);
+    }
+
+    #[test]
+    fn should_calculate_part_1_solution_in_parallel() {
+        let actual = part_1_parallel(EXAMPLE_INPUT);
+
+        assert_eq!(actual, 10605);
+    }
+
+    #[test]
+    fn should_calculate_part_2_solution_in_parallel() {
+        let actual = part_2_parallel(EXAMPLE_INPUT);
+
+        assert_eq!(actual, 2713310158);
+    }
 }
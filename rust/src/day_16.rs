@@ -1,10 +1,26 @@
 use std::{
     collections::{BinaryHeap, HashMap},
+    fmt::{Debug, Display, Write},
+    fs, io,
     ops::{Deref, DerefMut},
-    str::FromStr,
+    path::Path,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
 };
 
 use itertools::Itertools;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::digit1,
+    combinator::{all_consuming, map, map_res},
+    multi::separated_list1,
+    sequence::{preceded, tuple},
+    IResult,
+};
+use rayon::prelude::*;
 
 #[cfg(feature = "traced")]
 use tracing::instrument;
@@ -13,10 +29,59 @@ use tracing_chrome::ChromeLayerBuilder;
 #[cfg(feature = "traced")]
 use tracing_subscriber::{fmt, prelude::*, registry::Registry};
 
-use crate::utils::Day;
+use crate::utils::{has_flag, option_value, Day};
 
+/// An error in the description of the tunnel network.
+///
+/// The line numbers start at 1.
 #[derive(Debug, PartialEq, Eq)]
-struct ParseError;
+enum ParseError {
+    InvalidLine {
+        line: usize,
+        content: String,
+    },
+    DuplicateValve {
+        line: usize,
+        name: String,
+    },
+    UnknownValve {
+        line: usize,
+        name: String,
+    },
+    OneWayTunnel {
+        line: usize,
+        from: String,
+        to: String,
+    },
+    ValveCount {
+        expected: usize,
+        actual: usize,
+    },
+    MissingStartValve,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidLine { line, content } => {
+                write!(f, "Line {line}: Invalid valve description '{content}'")
+            }
+            ParseError::DuplicateValve { line, name } => {
+                write!(f, "Line {line}: Valve {name} is already defined")
+            }
+            ParseError::UnknownValve { line, name } => {
+                write!(f, "Line {line}: Tunnel leads to unknown valve {name}")
+            }
+            ParseError::OneWayTunnel { line, from, to } => {
+                write!(f, "Line {line}: Tunnel from {from} to {to} has no way back")
+            }
+            ParseError::ValveCount { expected, actual } => {
+                write!(f, "Expected {expected} valves, but found {actual}")
+            }
+            ParseError::MissingStartValve => write!(f, "The start valve AA is missing"),
+        }
+    }
+}
 
 type Pressure = u16;
 
@@ -30,44 +95,6 @@ struct ParsedValve {
     adjacent_valves: Vec<String>,
 }
 
-impl FromStr for ParsedValve {
-    type Err = ParseError;
-
-    #[cfg_attr(feature = "traced", instrument)]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tokens = &mut s.split_ascii_whitespace();
-
-        let Some(name) = tokens.nth(1) else {
-            return Err(ParseError);
-        };
-
-        let Ok(flow_rate) = (if let Some(rate_str) = tokens.nth(2) {
-            let Some((_, end_str)) = rate_str.split_once('=') else {
-                return Err(ParseError);
-            };
-
-            end_str[..(end_str.len() - 1)].parse::<Pressure>()
-        } else {
-            return Err(ParseError);
-        }) else {
-            return Err(ParseError)
-        };
-
-        let adjacent_valves: Vec<String> = tokens
-            .skip(4)
-            .collect::<String>()
-            .split(',')
-            .map(|s| s.to_string())
-            .collect();
-
-        Ok(Self {
-            name: name.to_string(),
-            flow_rate,
-            adjacent_valves,
-        })
-    }
-}
-
 impl PartialEq for ParsedValve {
     fn eq(&self, other: &Self) -> bool {
         self.name.eq(&other.name)
@@ -120,6 +147,25 @@ impl<const N: usize> TryFrom<Vec<Vec<ValveIndex>>> for AdjacentValves<N> {
     }
 }
 
+/// A map of the name for each valve.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct ValveNames<const N: usize>([String; N]);
+
+impl<const N: usize> ValveNames<N> {
+    fn get(&self, valve: ValveIndex) -> &str {
+        &self.0[valve as usize]
+    }
+}
+
+impl<const N: usize> TryFrom<Vec<String>> for ValveNames<N> {
+    type Error = Vec<String>;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        let valve_names: [String; N] = value.try_into()?;
+        Ok(Self(valve_names))
+    }
+}
+
 /// A list of the currently open valves.
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct OpenValves(Vec<ValveIndex>);
@@ -178,6 +224,7 @@ type MoveMap = HashMap<(ValveIndex, ValveIndex), Time>;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct GameInfo<const N: usize> {
+    valve_names: ValveNames<N>,
     flow_rates: FlowRates<N>,
     adjacent_valves: AdjacentValves<N>,
     total_time: Time,
@@ -230,45 +277,235 @@ impl<const N: usize> GameInfo<N> {
         let mut parsed_valves = s
             .trim()
             .lines()
-            .map(|line| line.parse())
-            .collect::<Result<Vec<ParsedValve>, ParseError>>()?;
+            .enumerate()
+            .map(|(idx, content)| {
+                let (_, valve) = all_consuming(parse_valve)(content.trim()).map_err(|_| {
+                    ParseError::InvalidLine {
+                        line: idx + 1,
+                        content: content.to_string(),
+                    }
+                })?;
+
+                Ok((idx + 1, valve))
+            })
+            .collect::<Result<Vec<(usize, ParsedValve)>, ParseError>>()?;
+
+        let mut defined_valves: HashMap<&str, &ParsedValve> = HashMap::new();
+
+        for (line, valve) in parsed_valves.iter() {
+            if defined_valves.insert(valve.name.as_str(), valve).is_some() {
+                return Err(ParseError::DuplicateValve {
+                    line: *line,
+                    name: valve.name.clone(),
+                });
+            }
+        }
+
+        for (line, valve) in parsed_valves.iter() {
+            for name in valve.adjacent_valves.iter() {
+                let Some(adjacent_valve) = defined_valves.get(name.as_str()) else {
+                    return Err(ParseError::UnknownValve {
+                        line: *line,
+                        name: name.clone(),
+                    });
+                };
+
+                // Every tunnel can be used in both directions
+                if !adjacent_valve.adjacent_valves.contains(&valve.name) {
+                    return Err(ParseError::OneWayTunnel {
+                        line: *line,
+                        from: valve.name.clone(),
+                        to: name.clone(),
+                    });
+                }
+            }
+        }
+
+        if parsed_valves.len() != N {
+            return Err(ParseError::ValveCount {
+                expected: N,
+                actual: parsed_valves.len(),
+            });
+        }
+
+        if !defined_valves.contains_key("AA") {
+            return Err(ParseError::MissingStartValve);
+        }
 
-        parsed_valves.sort();
+        // The search starts at index 0, so the start valve comes first
+        parsed_valves.sort_by(|(_, a), (_, b)| (a.name != "AA", a).cmp(&(b.name != "AA", b)));
 
         let index_map: HashMap<&str, ValveIndex> = parsed_valves
             .iter()
             .enumerate()
-            .map(|(index, parsed_valve)| (parsed_valve.name.as_str(), index as ValveIndex))
+            .map(|(index, (_, parsed_valve))| (parsed_valve.name.as_str(), index as ValveIndex))
             .collect();
 
         let adjacent_valves = parsed_valves
             .iter()
-            .map(|valve| {
+            .map(|(_, valve)| {
                 valve
                     .adjacent_valves
                     .iter()
-                    .map(|name| {
-                        *index_map
-                            .get(name.as_str())
-                            .unwrap_or_else(|| panic!("Can't map {name} to a valve index"))
-                    })
+                    .map(|name| index_map[name.as_str()])
                     .collect_vec()
             })
             .collect_vec();
-        let adjacent_valves = adjacent_valves.try_into().map_err(|_| ParseError)?;
 
         let flow_rates = parsed_valves
             .iter()
-            .map(|valve| valve.flow_rate)
+            .map(|(_, valve)| valve.flow_rate)
+            .collect_vec();
+
+        let valve_names = parsed_valves
+            .into_iter()
+            .map(|(_, valve)| valve.name)
             .collect_vec();
-        let flow_rates = flow_rates.try_into().map_err(|_| ParseError)?;
 
+        // The number of valves has already been checked
         Ok(Self {
-            adjacent_valves,
-            flow_rates,
+            valve_names: valve_names.try_into().unwrap(),
+            adjacent_valves: adjacent_valves.try_into().unwrap(),
+            flow_rates: flow_rates.try_into().unwrap(),
             total_time,
         })
     }
+
+    /// The same tunnel network, with a different amount of time.
+    fn with_total_time(&self, total_time: Time) -> Self {
+        Self {
+            total_time,
+            ..self.clone()
+        }
+    }
+
+    /// Render the raw tunnel network as a Graphviz DOT graph.
+    fn tunnels_dot(&self) -> String {
+        let mut dot = String::from("graph tunnels {\n");
+
+        for valve in 0..N as ValveIndex {
+            let name = self.valve_names.get(valve);
+            writeln!(
+                dot,
+                "    {name} [label=\"{name}\\n{}\"];",
+                self.flow_rate(valve)
+            )
+            .unwrap();
+        }
+
+        for valve in 0..N as ValveIndex {
+            for &adjacent in self.adjacent_valves.get(valve) {
+                // Only draw each tunnel once, unless it only leads in one direction
+                if valve < adjacent || !self.adjacent_valves.get(adjacent).contains(&valve) {
+                    writeln!(
+                        dot,
+                        "    {} -- {};",
+                        self.valve_names.get(valve),
+                        self.valve_names.get(adjacent)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the compressed network of the valves with flow as a Graphviz DOT graph.
+    ///
+    /// The edges are labeled with the time it takes to move between the valves.
+    /// The edges on the given routes (one per player) are highlighted.
+    fn move_map_dot(&self, move_map: &MoveMap, routes: &[Vec<ValveIndex>]) -> String {
+        const ROUTE_COLORS: [&str; 2] = ["red", "blue"];
+
+        let mut dot = String::from("graph moves {\n");
+
+        for valve in (0..N as ValveIndex).filter(|&valve| valve == 0 || self.flow_rate(valve) > 0) {
+            let name = self.valve_names.get(valve);
+            writeln!(
+                dot,
+                "    {name} [label=\"{name}\\n{}\"];",
+                self.flow_rate(valve)
+            )
+            .unwrap();
+        }
+
+        let route_edges: HashMap<(ValveIndex, ValveIndex), &str> = routes
+            .iter()
+            .zip(ROUTE_COLORS.iter())
+            // Let the first player's color win on shared edges
+            .rev()
+            .flat_map(|(route, &color)| {
+                route
+                    .iter()
+                    .tuple_windows()
+                    .map(move |(&from, &to)| ((from.min(to), from.max(to)), color))
+            })
+            .collect();
+
+        for (&(from, to), &time) in move_map.iter().sorted() {
+            // Only draw each connection once
+            if from == to || (from > to && move_map.contains_key(&(to, from))) {
+                continue;
+            }
+
+            let highlight = route_edges
+                .get(&(from.min(to), from.max(to)))
+                .map(|color| format!(", color={color}, penwidth=2"))
+                .unwrap_or_default();
+
+            writeln!(
+                dot,
+                "    {} -- {} [label=\"{time}\"{highlight}];",
+                self.valve_names.get(from),
+                self.valve_names.get(to)
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn parse_valve_name(input: &str) -> IResult<&str, String> {
+    map(
+        take_while1(|c: char| c.is_ascii_uppercase()),
+        |name: &str| name.to_string(),
+    )(input)
+}
+
+fn parse_flow_rate(input: &str) -> IResult<&str, Pressure> {
+    map_res(digit1, |num: &str| num.parse::<Pressure>())(input)
+}
+
+fn parse_tunnels(input: &str) -> IResult<&str, Vec<String>> {
+    let parse_single = preceded(
+        tag("; tunnel leads to valve "),
+        map(parse_valve_name, |name| vec![name]),
+    );
+    let parse_multiple = preceded(
+        tag("; tunnels lead to valves "),
+        separated_list1(tag(", "), parse_valve_name),
+    );
+
+    alt((parse_single, parse_multiple))(input)
+}
+
+fn parse_valve(input: &str) -> IResult<&str, ParsedValve> {
+    map(
+        tuple((
+            preceded(tag("Valve "), parse_valve_name),
+            preceded(tag(" has flow rate="), parse_flow_rate),
+            parse_tunnels,
+        )),
+        |(name, flow_rate, adjacent_valves)| ParsedValve {
+            name,
+            flow_rate,
+            adjacent_valves,
+        },
+    )(input)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -377,6 +614,183 @@ impl<const N: usize> PlayerState<N> {
     }
 }
 
+/// An upper bound for the pressure that can still be released in the remaining time.
+///
+/// To guarantee an optimal result of the search, the estimate must never be lower than
+/// the pressure that can actually still be released.
+trait Heuristic<const N: usize>: Debug {
+    fn estimate(
+        &self,
+        remaining_time: Time,
+        player_states: &[PlayerState<N>],
+        open_valves: &OpenValves,
+        info: &GameInfo<N>,
+    ) -> Pressure;
+}
+
+/// Assumes that every closed valve is opened as soon as any player can reach it.
+#[derive(Debug, Clone, Copy)]
+struct ReachTimeHeuristic;
+
+impl<const N: usize> Heuristic<N> for ReachTimeHeuristic {
+    #[cfg_attr(feature = "traced", instrument)]
+    fn estimate(
+        &self,
+        remaining_time: Time,
+        player_states: &[PlayerState<N>],
+        open_valves: &OpenValves,
+        info: &GameInfo<N>,
+    ) -> Pressure {
+        // The open valve can release the remaining pressure
+        let open_valve_value = open_valves
+            .iter()
+            .map(|&valve| info.flow_rate(valve) * remaining_time as Pressure)
+            .sum::<Pressure>();
+
+        // We can go to the closed valves and open them to release more pressure
+        // This is an upper bound, as we cannot go to multiple valves "at the same time"
+        let closed_valve_value = (0u8..N as u8)
+            // Only consider closed valves with flow
+            .filter(|valve| !open_valves.contains(valve) && info.flow_rate(*valve) > 0)
+            // Determine how quickly they can be reached
+            .map(|valve| {
+                let time_to_reach = player_states
+                    .iter()
+                    .map(|player_state| {
+                        if player_state.next_valve == valve {
+                            player_state.time_to_reach
+                        } else {
+                            let mut player_time = Time::MAX;
+
+                            for (v, time) in player_state.reachable_valves.iter() {
+                                if *v == valve {
+                                    // The player first has to reach the goal and then go to the new valve
+                                    player_time = player_state.time_to_reach + time;
+                                    break;
+                                }
+                            }
+
+                            player_time
+                        }
+                    })
+                    .min()
+                    .unwrap_or(Time::MAX);
+
+                let max_open_time = remaining_time.saturating_sub(time_to_reach);
+                max_open_time as Pressure * info.flow_rate(valve)
+            })
+            .sum::<Pressure>();
+
+        open_valve_value + closed_valve_value
+    }
+}
+
+/// Pairs the highest flow rates with the earliest times at which the players can open valves.
+///
+/// Each player needs at least two minutes for every valve after the one it is moving to:
+/// one to move to the valve and one to open it.
+#[derive(Debug, Clone, Copy)]
+struct SortedFlowHeuristic;
+
+impl<const N: usize> Heuristic<N> for SortedFlowHeuristic {
+    #[cfg_attr(feature = "traced", instrument)]
+    fn estimate(
+        &self,
+        remaining_time: Time,
+        player_states: &[PlayerState<N>],
+        open_valves: &OpenValves,
+        info: &GameInfo<N>,
+    ) -> Pressure {
+        // The open valve can release the remaining pressure
+        let open_valve_value = open_valves
+            .iter()
+            .map(|&valve| info.flow_rate(valve) * remaining_time as Pressure)
+            .sum::<Pressure>();
+
+        // The valves that the players are moving to will be opened at a known time
+        let pending_valves = player_states
+            .iter()
+            .filter(|player_state| {
+                !player_state.is_ready() && !open_valves.contains(&player_state.next_valve)
+            })
+            .map(|player_state| (player_state.next_valve, player_state.time_to_reach))
+            .sorted()
+            .dedup_by(|(valve, _), (other_valve, _)| valve == other_valve)
+            .collect_vec();
+
+        let pending_valve_value = pending_valves
+            .iter()
+            .map(|&(valve, time)| {
+                info.flow_rate(valve) * remaining_time.saturating_sub(time) as Pressure
+            })
+            .sum::<Pressure>();
+
+        // The earliest times at which the players can open any of the other valves
+        let open_times = player_states
+            .iter()
+            .filter_map(|player_state| {
+                player_state
+                    .reachable_valves
+                    .iter()
+                    .filter(|(valve, _)| !open_valves.contains(valve))
+                    .map(|&(_, time)| player_state.time_to_reach + time)
+                    .min()
+            })
+            .flat_map(|first_time| (first_time..remaining_time).step_by(2))
+            .sorted();
+
+        // The best case is that the highest flow rates get opened first
+        let closed_valve_value = player_states
+            .iter()
+            .flat_map(|player_state| player_state.reachable_valves.iter())
+            .map(|&(valve, _)| valve)
+            .filter(|valve| {
+                !open_valves.contains(valve)
+                    && !pending_valves
+                        .iter()
+                        .any(|(pending_valve, _)| pending_valve == valve)
+            })
+            .unique()
+            .map(|valve| info.flow_rate(valve))
+            .sorted_by(|a, b| b.cmp(a))
+            .zip(open_times)
+            .map(|(flow_rate, time)| flow_rate * (remaining_time - time) as Pressure)
+            .sum::<Pressure>();
+
+        let estimate = open_valve_value + pending_valve_value + closed_valve_value;
+
+        // Both bounds are valid, so we can take the tighter one
+        estimate.min(ReachTimeHeuristic.estimate(remaining_time, player_states, open_valves, info))
+    }
+}
+
+/// The valves that a player has opened, linked from the last one back to the first one.
+///
+/// States share the beginning of their routes, so cloning a route doesn't copy it.
+#[derive(Debug, Clone, Default)]
+struct Route(Option<Arc<(ValveIndex, Route)>>);
+
+impl Route {
+    fn push(&mut self, valve: ValveIndex) {
+        let previous = std::mem::take(self);
+        *self = Route(Some(Arc::new((valve, previous))));
+    }
+
+    /// The valves in the order in which they were opened.
+    fn to_vec(&self) -> Vec<ValveIndex> {
+        let mut valves = Vec::new();
+        let mut route = self;
+
+        while let Some(step) = &route.0 {
+            valves.push(step.0);
+            route = &step.1;
+        }
+
+        valves.reverse();
+        valves
+    }
+}
+
 #[derive(Debug, Clone)]
 struct GameState<const N: usize, const P: usize> {
     open_valves: OpenValves,
@@ -385,14 +799,22 @@ struct GameState<const N: usize, const P: usize> {
 
     player_states: [PlayerState<N>; P],
 
+    /// The valves each player has opened so far.
+    visited: [Route; P],
+
     /// An upper bound for the pressure that can still be released.
     heuristic: Pressure,
 }
 
 impl<const N: usize, const P: usize> GameState<N, P> {
-    fn start(info: &GameInfo<N>, move_map: &MoveMap, total_time: Time) -> Self {
+    fn start<H: Heuristic<N>>(
+        info: &GameInfo<N>,
+        move_map: &MoveMap,
+        total_time: Time,
+        heuristic: &H,
+    ) -> Self {
         let open_valves = OpenValves::new();
-        let player_states = (0..P)
+        let player_states: [PlayerState<N>; P] = (0..P)
             .map(|_| PlayerState::<N>::start(info, move_map))
             .collect_vec()
             .try_into()
@@ -401,9 +823,10 @@ impl<const N: usize, const P: usize> GameState<N, P> {
         Self {
             cur_minute: 0,
             cur_pressure_release: 0,
-            heuristic: Self::calculate_heuristic(total_time, &player_states, &open_valves, info),
+            heuristic: heuristic.estimate(total_time, &player_states, &open_valves, info),
             open_valves,
             player_states,
+            visited: std::array::from_fn(|_| Route::default()),
         }
     }
 
@@ -417,19 +840,28 @@ impl<const N: usize, const P: usize> GameState<N, P> {
             .unwrap();
 
         // The time passes while we move to the next valve and open it
-        self.cur_minute += tick_time;
+        self.tick(tick_time, info);
+    }
+
+    /// Let the given time pass, releasing pressure and moving the players forward.
+    fn tick(&mut self, time: Time, info: &GameInfo<N>) {
+        self.cur_minute += time;
 
         // Release pressure from the open valves during the time
-        self.cur_pressure_release += self.released_pressure(tick_time, info);
+        self.cur_pressure_release += self.released_pressure(time, info);
 
         // Move every player forward
-        self.player_states.iter_mut().for_each(|player_state| {
-            player_state.tick(tick_time);
-
-            if player_state.is_ready() {
-                player_state.execute_action(&mut self.open_valves);
-            }
-        });
+        self.player_states
+            .iter_mut()
+            .zip(self.visited.iter_mut())
+            .for_each(|(player_state, visited)| {
+                player_state.tick(time);
+
+                if player_state.is_ready() {
+                    player_state.execute_action(&mut self.open_valves);
+                    visited.push(player_state.next_valve);
+                }
+            });
     }
 
     #[cfg_attr(feature = "traced", instrument)]
@@ -479,18 +911,19 @@ impl<const N: usize, const P: usize> GameState<N, P> {
     }
 
     #[cfg_attr(feature = "traced", instrument)]
-    fn next_game_states(
+    fn next_game_states<H: Heuristic<N>>(
         &self,
         remaining_time: Time,
         next_player_states: Vec<[PlayerState<N>; P]>,
         info: &GameInfo<N>,
+        heuristic: &H,
     ) -> Vec<GameState<N, P>> {
         next_player_states
             .into_iter()
             .map(|player_states| {
                 let mut state = self.clone();
                 state.player_states = player_states;
-                state.heuristic = Self::calculate_heuristic(
+                state.heuristic = heuristic.estimate(
                     remaining_time,
                     &state.player_states,
                     &state.open_valves,
@@ -502,7 +935,12 @@ impl<const N: usize, const P: usize> GameState<N, P> {
     }
 
     #[cfg_attr(feature = "traced", instrument)]
-    fn expand(&mut self, info: &GameInfo<N>, move_map: &MoveMap) -> Vec<GameState<N, P>> {
+    fn expand<H: Heuristic<N>>(
+        &mut self,
+        info: &GameInfo<N>,
+        move_map: &MoveMap,
+        heuristic: &H,
+    ) -> Vec<GameState<N, P>> {
         // Pass time until the next action and release pressure from the open valves
         self.tick_to_next_action(info);
 
@@ -512,7 +950,7 @@ impl<const N: usize, const P: usize> GameState<N, P> {
 
         let next_player_states = self.next_player_states(remaining_time, info, move_map);
 
-        self.next_game_states(remaining_time, next_player_states, info)
+        self.next_game_states(remaining_time, next_player_states, info, heuristic)
     }
 
     #[cfg_attr(feature = "traced", instrument)]
@@ -522,59 +960,52 @@ impl<const N: usize, const P: usize> GameState<N, P> {
             .all(|p| p.reachable_valves.is_empty())
     }
 
-    #[cfg_attr(feature = "traced", instrument)]
-    fn calculate_heuristic(
-        remaining_time: Time,
-        player_states: &[PlayerState<N>; P],
-        open_valves: &OpenValves,
-        info: &GameInfo<N>,
-    ) -> Pressure {
-        // The open valve can release the remaining pressure
-        let open_valve_value = open_valves
-            .iter()
-            .map(|&valve| info.flow_rate(valve) * remaining_time as Pressure)
-            .sum::<Pressure>();
+    /// A lower bound for the total pressure released of this state.
+    ///
+    /// The valves that are already open will keep releasing pressure until the time runs out.
+    fn lower_bound(&self, info: &GameInfo<N>) -> Pressure {
+        let remaining_time = info.total_time.saturating_sub(self.cur_minute);
+        self.cur_pressure_release + self.released_pressure(remaining_time, info)
+    }
 
-        // We can go to the closed valves and open them to release more pressure
-        // This is an upper bound, as we cannot go to multiple valves "at the same time"
-        let closed_valve_value = (0u8..N as u8)
-            // Only consider closed valves with flow
-            .filter(|valve| !open_valves.contains(valve) && info.flow_rate(*valve) > 0)
-            // Determine how quickly they can be reached
-            .map(|valve| {
-                let time_to_reach = player_states
-                    .iter()
-                    .map(|player_state| {
-                        if player_state.next_valve == valve {
-                            player_state.time_to_reach
-                        } else {
-                            let mut player_time = Time::MAX;
+    /// The parts of the state that determine how much pressure can still be released.
+    fn key(&self) -> StateKey<P> {
+        let mut open_valves = [0; 4];
+        for &valve in self.open_valves.iter() {
+            open_valves[valve as usize / 64] |= 1 << (valve % 64);
+        }
 
-                            for (v, time) in player_state.reachable_valves.iter() {
-                                if *v == valve {
-                                    // The player first has to reach the goal and then go to the new valve
-                                    player_time = player_state.time_to_reach + time;
-                                    break;
-                                }
-                            }
+        let mut players = self
+            .player_states
+            .each_ref()
+            .map(|player_state| (player_state.next_valve, player_state.time_to_reach));
+        // The players are interchangeable
+        players.sort();
 
-                            player_time
-                        }
-                    })
-                    .min()
-                    .unwrap_or(Time::MAX);
+        StateKey {
+            cur_minute: self.cur_minute,
+            open_valves,
+            players,
+        }
+    }
 
-                let max_open_time = remaining_time.saturating_sub(time_to_reach);
-                max_open_time as Pressure * info.flow_rate(valve)
-            })
-            .sum::<Pressure>();
+    /// The route of valves that each player takes.
+    ///
+    /// This includes the valve that a player is currently moving to.
+    fn routes(&self) -> Vec<Vec<ValveIndex>> {
+        self.visited
+            .iter()
+            .zip(self.player_states.iter())
+            .map(|(visited, player_state)| {
+                let mut route = visited.to_vec();
 
-        // println!(
-        //     "Time: {remaining_time}, Open: {open_valve_value}, closed: {closed_valve_value}, total: {}",
-        //     open_valve_value + closed_valve_value
-        // );
+                if !player_state.is_ready() {
+                    route.push(player_state.next_valve);
+                }
 
-        open_valve_value + closed_valve_value
+                route
+            })
+            .collect()
     }
 
     /// An upper bound for the total pressure released of this state.
@@ -605,6 +1036,37 @@ impl<const N: usize, const P: usize> Ord for GameState<N, P> {
     }
 }
 
+/// Identifies game states that only differ in the pressure released so far.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct StateKey<const P: usize> {
+    cur_minute: Time,
+
+    /// One bit for every valve index that is open.
+    open_valves: [u64; 4],
+    players: [(ValveIndex, Time); P],
+}
+
+/// The most states that the search remembers as expanded, to limit its memory usage.
+const MAX_CLOSED_SET_SIZE: usize = 1 << 22;
+
+/// Statistics about the work done during a search.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+struct SearchStats {
+    nodes_expanded: usize,
+    max_heap_size: usize,
+    duplicate_states: usize,
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes expanded, max heap size {}, {} duplicate states",
+            self.nodes_expanded, self.max_heap_size, self.duplicate_states
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct PressureReleaseSearch<const N: usize> {
     info: GameInfo<N>,
@@ -616,27 +1078,421 @@ impl<const N: usize> PressureReleaseSearch<N> {
         Self { info, move_map }
     }
 
-    #[cfg_attr(feature = "traced", instrument)]
     fn search<const P: usize>(&self) -> GameState<N, P> {
+        let (result, _) = self.search_with_stats::<P, _>(&SortedFlowHeuristic);
+        result
+    }
+
+    #[cfg_attr(feature = "traced", instrument)]
+    fn search_with_stats<const P: usize, H: Heuristic<N>>(
+        &self,
+        heuristic: &H,
+    ) -> (GameState<N, P>, SearchStats) {
+        let mut stats = SearchStats::default();
+
+        // The most pressure released so far for each state that has already been expanded
+        let mut closed_set: HashMap<StateKey<P>, Pressure> = HashMap::new();
+
         // Do a modified A* search
         let mut open_set: BinaryHeap<GameState<N, P>> = BinaryHeap::new();
         open_set.push(GameState::<N, P>::start(
             &self.info,
             &self.move_map,
             self.info.total_time,
+            heuristic,
         ));
 
         while let Some(mut current) = open_set.pop() {
-            // println!("Current {current:?}\n");
             if current.is_leaf() {
-                return current;
+                return (current, stats);
+            }
+
+            // Skip states that can't release more pressure than an already expanded one
+            let key = current.key();
+            if let Some(&pressure) = closed_set.get(&key)
+                && pressure >= current.cur_pressure_release
+            {
+                stats.duplicate_states += 1;
+                continue;
             }
 
-            open_set.extend(current.expand(&self.info, &self.move_map).into_iter());
+            // When the closed set is full, the search continues without remembering new states
+            if closed_set.len() < MAX_CLOSED_SET_SIZE || closed_set.contains_key(&key) {
+                closed_set.insert(key, current.cur_pressure_release);
+            }
+
+            open_set.extend(current.expand(&self.info, &self.move_map, heuristic));
+
+            stats.nodes_expanded += 1;
+            stats.max_heap_size = stats.max_heap_size.max(open_set.len());
         }
 
         panic!("Unexpected end of search without result!");
     }
+
+    /// A branch and bound search that expands the best states of the frontier in parallel.
+    ///
+    /// The workers share the best known lower bound of the released pressure,
+    /// to prune all states that can't release more pressure.
+    #[cfg_attr(feature = "traced", instrument)]
+    fn parallel_search<const P: usize>(&self) -> GameState<N, P> {
+        let heuristic = SortedFlowHeuristic;
+        let batch_size = rayon::current_num_threads() * 4;
+
+        let best_lower_bound = AtomicU16::new(0);
+        let mut best_leaf: Option<GameState<N, P>> = None;
+
+        let mut frontier: BinaryHeap<GameState<N, P>> = BinaryHeap::new();
+        frontier.push(GameState::<N, P>::start(
+            &self.info,
+            &self.move_map,
+            self.info.total_time,
+            &heuristic,
+        ));
+
+        while let Some(top) = frontier.peek() {
+            // None of the remaining states can beat the best result anymore
+            if let Some(leaf) = &best_leaf
+                && top.score() <= leaf.score()
+            {
+                break;
+            }
+
+            let batch = (0..batch_size).map_while(|_| frontier.pop()).collect_vec();
+
+            let (children, leaves): (Vec<_>, Vec<_>) = batch
+                .into_par_iter()
+                .map(|mut state| {
+                    if state.score() < best_lower_bound.load(Ordering::Relaxed) {
+                        return (Vec::new(), None);
+                    }
+
+                    if state.is_leaf() {
+                        best_lower_bound.fetch_max(state.score(), Ordering::Relaxed);
+                        return (Vec::new(), Some(state));
+                    }
+
+                    let children = state.expand(&self.info, &self.move_map, &heuristic);
+                    best_lower_bound.fetch_max(state.lower_bound(&self.info), Ordering::Relaxed);
+
+                    (children, None)
+                })
+                .unzip();
+
+            // The states are ordered by their score, which is exact for leaves
+            best_leaf = best_leaf
+                .into_iter()
+                .chain(leaves.into_iter().flatten())
+                .max();
+
+            let bound = best_lower_bound.load(Ordering::Relaxed);
+            frontier.extend(
+                children
+                    .into_iter()
+                    .flatten()
+                    .filter(|child| child.score() >= bound),
+            );
+        }
+
+        best_leaf.expect("Unexpected end of search without result!")
+    }
+}
+
+/// A single step of a player's schedule, each one takes a minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScheduledAction {
+    Move(ValveIndex),
+    Open(ValveIndex),
+}
+
+/// An error in a schedule that can't be followed.
+///
+/// The steps start at 1, so they correspond to the minute of the action.
+#[derive(Debug, PartialEq, Eq)]
+enum ScheduleError {
+    PlayerCount {
+        expected: usize,
+        actual: usize,
+    },
+    UnknownValve {
+        player: usize,
+        step: usize,
+        name: String,
+    },
+    NoTunnel {
+        player: usize,
+        step: usize,
+        from: String,
+        to: String,
+    },
+    AlreadyOpen {
+        player: usize,
+        step: usize,
+        name: String,
+    },
+    OutOfTime {
+        player: usize,
+        steps: usize,
+        total_time: Time,
+    },
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::PlayerCount { expected, actual } => {
+                write!(
+                    f,
+                    "Expected a schedule for {expected} players, but got {actual}"
+                )
+            }
+            ScheduleError::UnknownValve { player, step, name } => {
+                write!(f, "Player {player}, step {step}: Unknown valve {name}")
+            }
+            ScheduleError::NoTunnel {
+                player,
+                step,
+                from,
+                to,
+            } => write!(
+                f,
+                "Player {player}, step {step}: No tunnel from {from} to {to}"
+            ),
+            ScheduleError::AlreadyOpen { player, step, name } => {
+                write!(
+                    f,
+                    "Player {player}, step {step}: Valve {name} is already open"
+                )
+            }
+            ScheduleError::OutOfTime {
+                player,
+                steps,
+                total_time,
+            } => write!(
+                f,
+                "Player {player}: {steps} steps don't fit into {total_time} minutes"
+            ),
+        }
+    }
+}
+
+/// The actions of each player, minute by minute.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Schedule<const P: usize>([Vec<ScheduledAction>; P]);
+
+impl<const P: usize> Schedule<P> {
+    /// Parse a schedule with one line per player.
+    ///
+    /// Each line lists the valves to move to, separated by whitespace.
+    /// `open` opens the valve the player is currently at.
+    fn parse<const N: usize>(s: &str, info: &GameInfo<N>) -> Result<Self, ScheduleError> {
+        let lines = s.trim().lines().collect_vec();
+
+        if lines.len() != P {
+            return Err(ScheduleError::PlayerCount {
+                expected: P,
+                actual: lines.len(),
+            });
+        }
+
+        let index_map: HashMap<&str, ValveIndex> = (0..N as ValveIndex)
+            .map(|valve| (info.valve_names.get(valve), valve))
+            .collect();
+
+        let mut open_valves = OpenValves::new();
+        let mut schedule: [Vec<ScheduledAction>; P] = std::array::from_fn(|_| Vec::new());
+
+        for (player, (line, actions)) in lines.iter().zip(schedule.iter_mut()).enumerate() {
+            let mut cur_valve = 0;
+
+            for (step, token) in line.split_ascii_whitespace().enumerate() {
+                let action = if token == "open" {
+                    if open_valves.contains(&cur_valve) {
+                        return Err(ScheduleError::AlreadyOpen {
+                            player: player + 1,
+                            step: step + 1,
+                            name: info.valve_names.get(cur_valve).to_string(),
+                        });
+                    }
+
+                    open_valves.open(cur_valve);
+                    ScheduledAction::Open(cur_valve)
+                } else {
+                    let Some(&valve) = index_map.get(token) else {
+                        return Err(ScheduleError::UnknownValve {
+                            player: player + 1,
+                            step: step + 1,
+                            name: token.to_string(),
+                        });
+                    };
+
+                    if !info.adjacent_valves.get(cur_valve).contains(&valve) {
+                        return Err(ScheduleError::NoTunnel {
+                            player: player + 1,
+                            step: step + 1,
+                            from: info.valve_names.get(cur_valve).to_string(),
+                            to: token.to_string(),
+                        });
+                    }
+
+                    cur_valve = valve;
+                    ScheduledAction::Move(valve)
+                };
+
+                actions.push(action);
+            }
+
+            if actions.len() > info.total_time as usize {
+                return Err(ScheduleError::OutOfTime {
+                    player: player + 1,
+                    steps: actions.len(),
+                    total_time: info.total_time,
+                });
+            }
+        }
+
+        Ok(Self(schedule))
+    }
+
+    /// The state of a player that follows the schedule from the given minute on.
+    ///
+    /// The player moves to the next valve that it opens.
+    /// If it doesn't open any more valves, it is never ready again.
+    fn player_state<const N: usize>(&self, player: usize, minute: Time) -> PlayerState<N> {
+        let actions = self.0[player].get(minute as usize..).unwrap_or_default();
+
+        let next_open = actions.iter().enumerate().find_map(|(idx, action)| {
+            if let ScheduledAction::Open(valve) = action {
+                Some((*valve, idx as Time + 1))
+            } else {
+                None
+            }
+        });
+
+        let (next_valve, time_to_reach) = next_open.unwrap_or((0, Time::MAX));
+
+        PlayerState {
+            next_valve,
+            time_to_reach,
+            reachable_valves: ReachableValves(Vec::new()),
+        }
+    }
+
+    /// Describe the action of the player in the given minute, like in the puzzle description.
+    fn describe_action<const N: usize>(
+        &self,
+        player: usize,
+        minute: Time,
+        info: &GameInfo<N>,
+    ) -> Option<String> {
+        let (subject, move_verb, open_verb) = if player == 0 {
+            ("You", "move", "open")
+        } else {
+            ("The elephant", "moves", "opens")
+        };
+
+        let description = match self.0[player].get(minute as usize)? {
+            ScheduledAction::Move(valve) => format!(
+                "{subject} {move_verb} to valve {}.",
+                info.valve_names.get(*valve)
+            ),
+            ScheduledAction::Open(valve) => format!(
+                "{subject} {open_verb} valve {}.",
+                info.valve_names.get(*valve)
+            ),
+        };
+
+        Some(description)
+    }
+
+    /// Follow the schedule minute by minute.
+    ///
+    /// Returns the total pressure released and a log in the style of the puzzle description.
+    fn simulate<const N: usize>(&self, info: &GameInfo<N>) -> (Pressure, String) {
+        let mut state = GameState::<N, P> {
+            open_valves: OpenValves::new(),
+            cur_pressure_release: 0,
+            cur_minute: 0,
+            player_states: std::array::from_fn(|player| self.player_state(player, 0)),
+            visited: std::array::from_fn(|_| Route::default()),
+            heuristic: 0,
+        };
+
+        let mut log = String::new();
+
+        while state.cur_minute < info.total_time {
+            writeln!(log, "== Minute {} ==", state.cur_minute + 1).unwrap();
+
+            let open_names = state
+                .open_valves
+                .iter()
+                .sorted()
+                .map(|&valve| info.valve_names.get(valve))
+                .collect_vec();
+            let released = state.released_pressure(1, info);
+
+            match open_names.as_slice() {
+                [] => writeln!(log, "No valves are open."),
+                [name] => writeln!(log, "Valve {name} is open, releasing {released} pressure."),
+                [first, second] => writeln!(
+                    log,
+                    "Valves {first} and {second} are open, releasing {released} pressure."
+                ),
+                [names @ .., last] => writeln!(
+                    log,
+                    "Valves {}, and {last} are open, releasing {released} pressure.",
+                    names.join(", ")
+                ),
+            }
+            .unwrap();
+
+            for player in 0..P {
+                if let Some(description) = self.describe_action(player, state.cur_minute, info) {
+                    writeln!(log, "{description}").unwrap();
+                }
+            }
+
+            log.push('\n');
+
+            state.tick(1, info);
+
+            // Players that just opened a valve continue with the rest of their schedule
+            for (player, player_state) in state.player_states.iter_mut().enumerate() {
+                if player_state.is_ready() {
+                    *player_state = self.player_state(player, state.cur_minute);
+                }
+            }
+        }
+
+        (state.cur_pressure_release, log)
+    }
+}
+
+/// Simulate the schedule in the given file and print the log.
+///
+/// A schedule with one player has 30 minutes, a schedule with two players 26 minutes.
+fn simulate_file<const N: usize>(info: &GameInfo<N>, path: &Path) -> Result<(), String> {
+    let schedule = fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    let (released, log) = match schedule.trim().lines().count() {
+        1 => {
+            let info = info.with_total_time(30);
+            Schedule::<1>::parse(&schedule, &info)
+                .map_err(|err| err.to_string())?
+                .simulate(&info)
+        }
+        _ => {
+            let info = info.with_total_time(26);
+            Schedule::<2>::parse(&schedule, &info)
+                .map_err(|err| err.to_string())?
+                .simulate(&info)
+        }
+    };
+
+    print!("{log}");
+    println!("Total pressure released: {released}");
+
+    Ok(())
 }
 
 pub struct Day16;
@@ -654,14 +1510,88 @@ impl Day for Day16 {
 
         let input = self.get_input();
 
-        println!("Part 1: {}", part_1::<59>(&input));
-        println!("Part 2: {}", part_2::<59>(&input));
+        let info = match GameInfo::<59>::from_str(&input, 30) {
+            Ok(info) => info,
+            Err(err) => {
+                println!("Invalid input: {err}");
+                return;
+            }
+        };
+
+        if let Some(path) = option_value("--simulate") {
+            if let Err(err) = simulate_file(&info, Path::new(&path)) {
+                println!("Invalid schedule: {err}");
+            }
+            return;
+        }
+
+        if has_flag("--parallel") {
+            println!("Part 1: {}", part_1_parallel(&info));
+            println!("Part 2: {}", part_2_parallel(&info));
+        } else {
+            println!("Part 1: {}", part_1(&info));
+            println!("Part 2: {}", part_2(&info));
+        }
+
+        if has_flag("--stats") {
+            print_stats(&info);
+        }
+
+        if let Some(dir) = option_value("--dot") {
+            export_dot(&info, Path::new(&dir)).expect("Failed to write DOT files");
+            println!("\nWrote DOT graphs to {dir}");
+        }
     }
 }
 
+/// Print how much work the search does with each of the heuristics.
+fn print_stats<const N: usize>(info: &GameInfo<N>) {
+    println!("\nSearch statistics:");
+    print_part_stats::<N, 1>(info.with_total_time(30));
+    print_part_stats::<N, 2>(info.with_total_time(26));
+}
+
+fn print_part_stats<const N: usize, const P: usize>(info: GameInfo<N>) {
+    let move_map = info.compute_move_map();
+    let pressure_search = PressureReleaseSearch::new(info, move_map);
+
+    let (_, stats) = pressure_search.search_with_stats::<P, _>(&ReachTimeHeuristic);
+    println!("Part {P} (reach time heuristic): {stats}");
+
+    let (_, stats) = pressure_search.search_with_stats::<P, _>(&SortedFlowHeuristic);
+    println!("Part {P} (sorted flow heuristic): {stats}");
+}
+
+/// Write the tunnel network and the compressed move graphs with the optimal routes as DOT files.
+fn export_dot<const N: usize>(info: &GameInfo<N>, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let info = info.with_total_time(30);
+    fs::write(dir.join("day_16_tunnels.dot"), info.tunnels_dot())?;
+
+    let move_map = info.compute_move_map();
+    let pressure_search = PressureReleaseSearch::new(info.clone(), move_map.clone());
+    let routes = pressure_search.search::<1>().routes();
+    fs::write(
+        dir.join("day_16_moves_part_1.dot"),
+        info.move_map_dot(&move_map, &routes),
+    )?;
+
+    let info = info.with_total_time(26);
+    let move_map = info.compute_move_map();
+    let pressure_search = PressureReleaseSearch::new(info.clone(), move_map.clone());
+    let routes = pressure_search.search::<2>().routes();
+    fs::write(
+        dir.join("day_16_moves_part_2.dot"),
+        info.move_map_dot(&move_map, &routes),
+    )?;
+
+    Ok(())
+}
+
 #[cfg_attr(feature = "traced", instrument)]
-fn part_1<const N: usize>(input: &str) -> Pressure {
-    let info = GameInfo::<N>::from_str(input, 30).unwrap();
+fn part_1<const N: usize>(info: &GameInfo<N>) -> Pressure {
+    let info = info.with_total_time(30);
     let move_map = info.compute_move_map();
 
     let pressure_search = PressureReleaseSearch::new(info, move_map);
@@ -670,8 +1600,8 @@ fn part_1<const N: usize>(input: &str) -> Pressure {
 }
 
 #[cfg_attr(feature = "traced", instrument)]
-fn part_2<const N: usize>(input: &str) -> Pressure {
-    let info: GameInfo<N> = GameInfo::<N>::from_str(input, 26).unwrap();
+fn part_2<const N: usize>(info: &GameInfo<N>) -> Pressure {
+    let info = info.with_total_time(26);
     let move_map = info.compute_move_map();
 
     let pressure_search = PressureReleaseSearch::new(info, move_map);
@@ -679,6 +1609,26 @@ fn part_2<const N: usize>(input: &str) -> Pressure {
     result.score()
 }
 
+#[cfg_attr(feature = "traced", instrument)]
+fn part_1_parallel<const N: usize>(info: &GameInfo<N>) -> Pressure {
+    let info = info.with_total_time(30);
+    let move_map = info.compute_move_map();
+
+    let pressure_search = PressureReleaseSearch::new(info, move_map);
+    let result = pressure_search.parallel_search::<1>();
+    result.score()
+}
+
+#[cfg_attr(feature = "traced", instrument)]
+fn part_2_parallel<const N: usize>(info: &GameInfo<N>) -> Pressure {
+    let info = info.with_total_time(26);
+    let move_map = info.compute_move_map();
+
+    let pressure_search = PressureReleaseSearch::new(info, move_map);
+    let result = pressure_search.parallel_search::<2>();
+    result.score()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -700,6 +1650,9 @@ Valve JJ has flow rate=21; tunnel leads to valve II
     fn should_parse_game_info() {
         let actual = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
         let expected = GameInfo::<10> {
+            valve_names: ValveNames(
+                ["AA", "BB", "CC", "DD", "EE", "FF", "GG", "HH", "II", "JJ"].map(String::from),
+            ),
             adjacent_valves: AdjacentValves([
                 vec![3, 8, 1],
                 vec![2, 0],
@@ -731,6 +1684,7 @@ Valve JJ has flow rate=21; tunnel leads to valve II
             10,
             &OpenValves(vec![0]),
             &GameInfo {
+                valve_names: ValveNames(["AA", "BB", "CC"].map(String::from)),
                 flow_rates: FlowRates([2; 3]),
                 adjacent_valves: AdjacentValves([vec![2], vec![0], vec![1]]),
                 total_time: 10,
@@ -759,6 +1713,7 @@ Valve JJ has flow rate=21; tunnel leads to valve II
         move_map.insert((2, 1), 1);
 
         let info = GameInfo {
+            valve_names: ValveNames(["AA", "BB", "CC"].map(String::from)),
             flow_rates: FlowRates([2; 3]),
             adjacent_valves: AdjacentValves([vec![2], vec![0], vec![0, 1]]),
             total_time: 10,
@@ -781,16 +1736,302 @@ Valve JJ has flow rate=21; tunnel leads to valve II
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn should_parse_valve() {
+        let actual = parse_valve("Valve HH has flow rate=22; tunnel leads to valve GG");
+
+        assert!(matches!(
+            actual,
+            Ok(("", ParsedValve { name, flow_rate: 22, adjacent_valves }))
+                if name == "HH" && adjacent_valves == vec!["GG".to_string()]
+        ));
+
+        let actual = parse_valve("Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE");
+
+        assert!(matches!(
+            actual,
+            Ok(("", ParsedValve { name, flow_rate: 20, adjacent_valves }))
+                if name == "DD" && adjacent_valves == vec!["CC", "AA", "EE"]
+        ));
+    }
+
+    #[test]
+    fn should_require_start_valve() {
+        let input = "Valve BB has flow rate=0; tunnel leads to valve CC
+Valve CC has flow rate=3; tunnel leads to valve BB";
+
+        let actual = GameInfo::<2>::from_str(input, 30);
+
+        assert_eq!(actual, Err(ParseError::MissingStartValve));
+    }
+
+    #[test]
+    fn should_start_at_index_0() {
+        let input = "Valve AA has flow rate=0; tunnel leads to valve A
+Valve A has flow rate=3; tunnel leads to valve AA";
+
+        let actual = GameInfo::<2>::from_str(input, 30).unwrap();
+
+        assert_eq!(actual.valve_names.get(0), "AA");
+    }
+
+    #[test]
+    fn should_report_invalid_line() {
+        let input = "Valve AA has flow rate=0; tunnel leads to valve BB
+Valve BB has flow rate=x; tunnel leads to valve AA";
+
+        let actual = GameInfo::<2>::from_str(input, 30);
+        let expected = ParseError::InvalidLine {
+            line: 2,
+            content: "Valve BB has flow rate=x; tunnel leads to valve AA".to_string(),
+        };
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_reject_duplicate_valves() {
+        let input = "Valve AA has flow rate=0; tunnel leads to valve BB
+Valve BB has flow rate=3; tunnel leads to valve AA
+Valve AA has flow rate=2; tunnel leads to valve BB";
+
+        let actual = GameInfo::<3>::from_str(input, 30);
+        let expected = ParseError::DuplicateValve {
+            line: 3,
+            name: "AA".to_string(),
+        };
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_reject_unknown_valves() {
+        let input = "Valve AA has flow rate=0; tunnels lead to valves BB, CC
+Valve BB has flow rate=3; tunnel leads to valve AA";
+
+        let actual = GameInfo::<2>::from_str(input, 30);
+        let expected = ParseError::UnknownValve {
+            line: 1,
+            name: "CC".to_string(),
+        };
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_reject_one_way_tunnels() {
+        let input = "Valve AA has flow rate=0; tunnel leads to valve BB
+Valve BB has flow rate=3; tunnel leads to valve CC
+Valve CC has flow rate=1; tunnel leads to valve BB";
+
+        let actual = GameInfo::<3>::from_str(input, 30);
+        let expected = ParseError::OneWayTunnel {
+            line: 1,
+            from: "AA".to_string(),
+            to: "BB".to_string(),
+        };
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_render_tunnels_dot() {
+        let info = GameInfo::<3> {
+            valve_names: ValveNames(["AA", "BB", "CC"].map(String::from)),
+            flow_rates: FlowRates([0, 5, 0]),
+            adjacent_valves: AdjacentValves([vec![1], vec![0, 2], vec![1]]),
+            total_time: 10,
+        };
+
+        let actual = info.tunnels_dot();
+        let expected = "graph tunnels {
+    AA [label=\"AA\\n0\"];
+    BB [label=\"BB\\n5\"];
+    CC [label=\"CC\\n0\"];
+    AA -- BB;
+    BB -- CC;
+}
+";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_render_move_map_dot_with_route() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
+        let move_map = info.compute_move_map();
+
+        let actual = info.move_map_dot(&move_map, &[vec![0, 3, 1]]);
+
+        assert!(actual.contains("    AA -- DD [label=\"1\", color=red, penwidth=2];\n"));
+        assert!(actual.contains("    BB -- DD [label=\"2\", color=red, penwidth=2];\n"));
+        assert!(actual.contains("    AA -- JJ [label=\"2\"];\n"));
+        assert!(!actual.contains("FF"));
+    }
+
+    #[test]
+    fn should_find_optimal_route() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
+        let move_map = info.compute_move_map();
+        let pressure_search = PressureReleaseSearch::new(info, move_map);
+
+        let actual = pressure_search.search::<1>().routes();
+
+        assert_eq!(actual, vec![vec![0, 3, 1, 9, 7, 4, 2]]);
+    }
+
+    #[test]
+    fn should_estimate_tighter_bound_with_sorted_flow_heuristic() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
+        let move_map = info.compute_move_map();
+        let player_states = [PlayerState::<10>::start(&info, &move_map)];
+        let open_valves = OpenValves::new();
+
+        let loose = ReachTimeHeuristic.estimate(30, &player_states, &open_valves, &info);
+        let tight = SortedFlowHeuristic.estimate(30, &player_states, &open_valves, &info);
+
+        assert!(tight < loose);
+        assert!(tight >= 1651);
+    }
+
+    #[test]
+    fn should_find_same_result_with_both_heuristics() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 26).unwrap();
+        let move_map = info.compute_move_map();
+        let pressure_search = PressureReleaseSearch::new(info, move_map);
+
+        let (loose_result, loose_stats) =
+            pressure_search.search_with_stats::<2, _>(&ReachTimeHeuristic);
+        let (tight_result, tight_stats) =
+            pressure_search.search_with_stats::<2, _>(&SortedFlowHeuristic);
+
+        assert_eq!(loose_result.score(), 1707);
+        assert_eq!(tight_result.score(), 1707);
+        assert!(tight_stats.nodes_expanded <= loose_stats.nodes_expanded);
+    }
+
+    #[test]
+    fn should_detect_duplicate_states() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 26).unwrap();
+        let move_map = info.compute_move_map();
+        let pressure_search = PressureReleaseSearch::new(info, move_map);
+
+        let (_, stats) = pressure_search.search_with_stats::<2, _>(&ReachTimeHeuristic);
+
+        assert!(stats.duplicate_states > 0);
+        assert!(stats.max_heap_size > 0);
+    }
+
     #[test]
     fn should_calculate_part_1_solution() {
-        let actual = part_1::<10>(EXAMPLE_INPUT);
+        let actual = part_1(&GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap());
 
         assert_eq!(actual, 1651);
     }
 
     #[test]
     fn should_calculate_part_2_solution() {
-        let actual = part_2::<10>(EXAMPLE_INPUT);
+        let actual = part_2(&GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap());
+
+        assert_eq!(actual, 1707);
+    }
+
+    #[test]
+    fn should_simulate_schedule_for_one_player() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
+        let schedule = Schedule::<1>::parse(
+            "DD open CC BB open AA II JJ open II AA DD EE FF GG HH open GG FF EE open DD CC open",
+            &info,
+        )
+        .unwrap();
+
+        let (released, log) = schedule.simulate(&info);
+
+        assert_eq!(released, 1651);
+        assert!(log.starts_with(
+            "== Minute 1 ==
+No valves are open.
+You move to valve DD.
+
+== Minute 2 ==
+No valves are open.
+You open valve DD.
+
+== Minute 3 ==
+Valve DD is open, releasing 20 pressure.
+You move to valve CC.
+"
+        ));
+        assert!(log.ends_with(
+            "== Minute 30 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.
+
+"
+        ));
+    }
+
+    #[test]
+    fn should_simulate_schedule_for_two_players() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 26).unwrap();
+        let schedule = Schedule::<2>::parse(
+            "II JJ open II AA BB open CC open
+DD open EE FF GG HH open GG FF EE open",
+            &info,
+        )
+        .unwrap();
+
+        let (released, log) = schedule.simulate(&info);
+
+        assert_eq!(released, 1707);
+        assert!(log.contains(
+            "== Minute 3 ==
+Valve DD is open, releasing 20 pressure.
+You open valve JJ.
+The elephant moves to valve EE.
+"
+        ));
+    }
+
+    #[test]
+    fn should_reject_invalid_schedules() {
+        let info = GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap();
+
+        let actual = Schedule::<1>::parse("DD open EE FF HH", &info);
+        let expected = ScheduleError::NoTunnel {
+            player: 1,
+            step: 5,
+            from: "FF".to_string(),
+            to: "HH".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = Schedule::<1>::parse("DD open AA DD open", &info);
+        let expected = ScheduleError::AlreadyOpen {
+            player: 1,
+            step: 5,
+            name: "DD".to_string(),
+        };
+        assert_eq!(actual, Err(expected));
+
+        let actual = Schedule::<1>::parse(&"DD AA ".repeat(16), &info);
+        let expected = ScheduleError::OutOfTime {
+            player: 1,
+            steps: 32,
+            total_time: 30,
+        };
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn should_calculate_part_1_solution_in_parallel() {
+        let actual = part_1_parallel(&GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap());
+
+        assert_eq!(actual, 1651);
+    }
+
+    #[test]
+    fn should_calculate_part_2_solution_in_parallel() {
+        let actual = part_2_parallel(&GameInfo::<10>::from_str(EXAMPLE_INPUT, 30).unwrap());
 
         assert_eq!(actual, 1707);
     }
@@ -1,4 +1,8 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Write},
     ops::{Deref, DerefMut},
     str::FromStr,
 };
@@ -6,183 +10,814 @@ use std::{
 #[derive(Debug, PartialEq, Eq)]
 struct ParserError;
 
-use crate::utils::Day;
+use itertools::Itertools;
 
-type Coord = i8;
+use crate::utils::{has_flag, option_value, Day};
 
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-struct Droplet(Coord, Coord, Coord);
+type Coord = i32;
 
-impl Droplet {
-    fn neighbors(&self) -> Vec<Droplet> {
-        let (x, y, z) = (self.0, self.1, self.2);
+/// A cube in any number of dimensions, three for the lava droplets.
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+struct Droplet<const N: usize = 3>([Coord; N]);
 
-        vec![
-            Droplet(x + 1, y, z),
-            Droplet(x - 1, y, z),
-            Droplet(x, y + 1, z),
-            Droplet(x, y - 1, z),
-            Droplet(x, y, z + 1),
-            Droplet(x, y, z - 1),
-        ]
+impl<const N: usize> FromStr for Droplet<N> {
+    type Err = ParserError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords: Vec<Coord> = s
+            .split(',')
+            .map(|token| token.parse().map_err(|_| ParserError))
+            .collect::<Result<_, _>>()?;
+
+        coords.try_into().map(Droplet).map_err(|_| ParserError)
+    }
+}
+
+impl<const N: usize> Display for Droplet<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.iter().join(","))
     }
 }
 
-impl FromStr for Droplet {
+/// Which cells of air are connected, so that air can flow between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Connectivity {
+    /// Cells that share a face.
+    #[default]
+    Face,
+
+    /// Cells that share at least an edge, so air can flow through diagonal gaps.
+    Edge,
+
+    /// Cells that share at least a corner.
+    Corner,
+}
+
+impl Connectivity {
+    /// The offsets to the neighbors of a cell, starting with the faces in order of the axes.
+    fn offsets<const N: usize>(self) -> Vec<[isize; N]> {
+        let max_changed_axes = match self {
+            Connectivity::Face => 1,
+            Connectivity::Edge => 2,
+            Connectivity::Corner => N,
+        };
+
+        let mut offsets: Vec<[isize; N]> = (0..3usize.pow(N as u32))
+            .map(|mut digits| {
+                let mut offset = [0; N];
+
+                for delta in offset.iter_mut() {
+                    *delta = (digits % 3) as isize - 1;
+                    digits /= 3;
+                }
+
+                offset
+            })
+            .filter(|offset| {
+                let changed_axes = offset.iter().filter(|&&delta| delta != 0).count();
+                changed_axes > 0 && changed_axes <= max_changed_axes
+            })
+            .collect();
+
+        offsets.sort_by_key(|offset| {
+            let changed_axes = offset.iter().filter(|&&delta| delta != 0).count();
+            let first_axis = offset.iter().position(|&delta| delta != 0);
+
+            (changed_axes, first_axis, offset.iter().sum::<isize>())
+        });
+
+        offsets
+    }
+}
+
+impl FromStr for Connectivity {
     type Err = ParserError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut tokens = s.split(',');
+        match s {
+            "face" => Ok(Connectivity::Face),
+            "edge" => Ok(Connectivity::Edge),
+            "corner" => Ok(Connectivity::Corner),
+            _ => Err(ParserError),
+        }
+    }
+}
+
+/// The index of a voxel in the grid, on every axis.
+type Voxel<const N: usize> = [usize; N];
+
+/// The most voxels that a grid can have, to not run out of memory.
+const MAX_VOXELS: usize = 1 << 30;
+
+/// A dense grid of voxels around the droplets, with constant time lookups.
+///
+/// The grid has a layer of air around the droplets, so that the air can flow around them.
+/// Coordinates that are far from every droplet on an axis are merged into a single voxel,
+/// so that the size of the grid doesn't depend on how far apart the droplets are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VoxelGrid<const N: usize> {
+    /// The coordinate of every voxel on each axis, sorted.
+    ///
+    /// They are wider than `Coord`, because the layer of air can be outside of its range.
+    coords: [Vec<i64>; N],
+    size: Voxel<N>,
+
+    /// One bit for every voxel, which is set if it contains a droplet.
+    bits: Vec<u64>,
+
+    /// The offsets to the voxels that share a face.
+    faces: Vec<[isize; N]>,
+
+    /// The offsets to the voxels that air can flow to.
+    connections: Vec<[isize; N]>,
+}
+
+impl<const N: usize> VoxelGrid<N> {
+    fn new(droplets: &Droplets<N>, connectivity: Connectivity) -> Self {
+        let faces = Connectivity::Face.offsets();
+        let connections = connectivity.offsets();
+
+        // Every droplet and the air next to it get their own voxel
+        let coords: [Vec<i64>; N] = std::array::from_fn(|axis| {
+            let mut near_droplets: Vec<i64> = droplets
+                .iter()
+                .flat_map(|droplet| {
+                    let coord = droplet.0[axis] as i64;
+                    [coord - 1, coord, coord + 1]
+                })
+                .collect();
+            near_droplets.sort_unstable();
+            near_droplets.dedup();
 
-        if let Some(x_str) = tokens.next() && let Some(y_str) = tokens.next() && let Some(z_str) = tokens.next() {
-            if let Ok(x) = x_str.parse::<Coord>() && let Ok(y) = y_str.parse::<Coord>() && let Ok(z) = z_str.parse::<Coord>() {
-                Ok(Droplet(x, y, z))
-            } else {
-                Err(ParserError)
+            // The air between the droplets can't be enclosed on this axis, so a single voxel
+            // for it keeps the air on both sides connected
+            let mut coords = Vec::with_capacity(near_droplets.len());
+
+            for coord in near_droplets {
+                if let Some(&last) = coords.last()
+                    && coord > last + 1
+                {
+                    coords.push(last + 1);
+                }
+
+                coords.push(coord);
+            }
+
+            coords
+        });
+        let size = coords.each_ref().map(Vec::len);
+
+        let len = size
+            .iter()
+            .try_fold(1usize, |len, &size| len.checked_mul(size))
+            .filter(|&len| len <= MAX_VOXELS)
+            .expect("The droplets are spread over too many coordinates for a voxel grid");
+
+        let mut grid = Self {
+            coords,
+            size,
+            bits: vec![0; len.div_ceil(64)],
+            faces,
+            connections,
+        };
+
+        for droplet in droplets.iter() {
+            let index = grid.index(grid.voxel(droplet));
+            grid.bits[index / 64] |= 1 << (index % 64);
+        }
+
+        grid
+    }
+
+    /// The number of voxels in the grid.
+    fn len(&self) -> usize {
+        self.size.iter().product()
+    }
+
+    /// The voxel of the given droplet.
+    ///
+    /// The droplet must be one of the droplets that the grid was created with.
+    fn voxel(&self, droplet: &Droplet<N>) -> Voxel<N> {
+        std::array::from_fn(|axis| {
+            self.coords[axis]
+                .binary_search(&(droplet.0[axis] as i64))
+                .expect("The droplet should be in the grid")
+        })
+    }
+
+    fn index(&self, voxel: Voxel<N>) -> usize {
+        voxel
+            .iter()
+            .zip(self.size)
+            .fold(0, |index, (coord, size)| index * size + coord)
+    }
+
+    /// Determine if the voxel contains a droplet.
+    fn contains(&self, voxel: Voxel<N>) -> bool {
+        let index = self.index(voxel);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// The droplet coordinates of the voxel.
+    ///
+    /// The voxel must be within the bounds of the droplets, not in the surrounding layer of air.
+    /// Enclosed air is never merged, so it always has the coordinates of a single cell.
+    fn droplet(&self, voxel: Voxel<N>) -> Droplet<N> {
+        Droplet(std::array::from_fn(|axis| {
+            self.coords[axis][voxel[axis]] as Coord
+        }))
+    }
+
+    /// All voxels of the grid.
+    fn voxels(&self) -> impl Iterator<Item = Voxel<N>> + '_ {
+        (0..self.len()).map(|mut index| {
+            let mut voxel = [0; N];
+
+            for axis in (0..N).rev() {
+                voxel[axis] = index % self.size[axis];
+                index /= self.size[axis];
+            }
+
+            voxel
+        })
+    }
+
+    /// Fill the air connected to the start voxel and mark it as visited.
+    ///
+    /// Returns the filled voxels and the number of droplet faces that the air touches.
+    fn flood_fill(&self, start: Voxel<N>, visited: &mut [bool]) -> (Vec<Voxel<N>>, usize) {
+        let mut filled = vec![start];
+        let mut stack = vec![start];
+        visited[self.index(start)] = true;
+        let mut surface_area = 0;
+
+        while let Some(air) = stack.pop() {
+            surface_area += self
+                .neighbors(air)
+                .filter(|&neighbor| self.contains(neighbor))
+                .count();
+
+            for neighbor in self.connected(air) {
+                if !self.contains(neighbor) && !visited[self.index(neighbor)] {
+                    visited[self.index(neighbor)] = true;
+                    filled.push(neighbor);
+                    stack.push(neighbor);
+                }
             }
-        } else {
-            Err(ParserError)
         }
+
+        (filled, surface_area)
+    }
+
+    /// The voxels that share a face with the given voxel and are inside of the grid.
+    fn neighbors(&self, voxel: Voxel<N>) -> impl Iterator<Item = Voxel<N>> + '_ {
+        self.faces
+            .iter()
+            .filter_map(move |offset| self.offset(voxel, offset))
+    }
+
+    /// The voxels that air can flow to from the given voxel and are inside of the grid.
+    fn connected(&self, voxel: Voxel<N>) -> impl Iterator<Item = Voxel<N>> + '_ {
+        self.connections
+            .iter()
+            .filter_map(move |offset| self.offset(voxel, offset))
+    }
+
+    /// Move the voxel by the offset, if the result is inside of the grid.
+    fn offset(&self, voxel: Voxel<N>, offset: &[isize; N]) -> Option<Voxel<N>> {
+        let mut moved = voxel;
+
+        for axis in 0..N {
+            moved[axis] = voxel[axis].checked_add_signed(offset[axis])?;
+
+            if moved[axis] >= self.size[axis] {
+                return None;
+            }
+        }
+
+        Some(moved)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Droplets(Vec<Droplet>);
+struct Droplets<const N: usize = 3>(Vec<Droplet<N>>);
 
-impl Droplets {
+impl<const N: usize> Droplets<N> {
     fn surface_area(&self) -> usize {
+        let grid = VoxelGrid::new(self, Connectivity::Face);
+
         self.iter()
             .map(|droplet| {
-                droplet
-                    .neighbors()
-                    .iter()
+                grid.neighbors(grid.voxel(droplet))
                     // Determine how many sides are free
-                    .filter(|neighbor| self.0.binary_search(neighbor).is_err())
+                    .filter(|&neighbor| !grid.contains(neighbor))
                     .count()
             })
             .sum()
     }
 
-    fn exterior_surface_area(&self) -> usize {
-        // TODO: There can also be holes in the cube that are not closed up
-        let mut surface_area = 0;
-
-        let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = self.iter().fold(
-            (
-                (Coord::MAX, Coord::MAX, Coord::MAX),
-                (Coord::MIN, Coord::MIN, Coord::MIN),
-            ),
-            |((x_min, y_min, z_min), (x_max, y_max, z_max)), droplet| {
+    /// The smallest and largest coordinates on every axis.
+    fn bounds(&self) -> (Droplet<N>, Droplet<N>) {
+        self.iter().fold(
+            (Droplet([Coord::MAX; N]), Droplet([Coord::MIN; N])),
+            |(min, max), droplet| {
                 (
-                    (
-                        x_min.min(droplet.0),
-                        y_min.min(droplet.1),
-                        z_min.min(droplet.2),
-                    ),
-                    (
-                        x_max.max(droplet.0),
-                        y_max.max(droplet.1),
-                        z_max.max(droplet.2),
-                    ),
+                    Droplet(std::array::from_fn(|axis| min.0[axis].min(droplet.0[axis]))),
+                    Droplet(std::array::from_fn(|axis| max.0[axis].max(droplet.0[axis]))),
                 )
             },
-        );
+        )
+    }
 
-        // Check surfaces on x-axis
-        for y in y_min..=y_max {
-            for z in z_min..=z_max {
-                for x in (x_min - 1)..=(x_max + 1) {
-                    let droplet = Droplet(x, y, z);
+    /// The surface area that can be reached from the outside.
+    ///
+    /// Flood fills the air in the voxel grid, starting in a corner,
+    /// and counts the faces of droplets that the air touches.
+    fn exterior_surface_area(&self, connectivity: Connectivity) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
-                    }
+        let grid = VoxelGrid::new(self, connectivity);
+        let mut visited = vec![false; grid.len()];
+
+        let (_, surface_area) = grid.flood_fill([0; N], &mut visited);
+        surface_area
+    }
+
+    /// The pockets of air that are enclosed by the droplets.
+    fn air_pockets(&self, connectivity: Connectivity) -> Vec<AirPocket<N>> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let grid = VoxelGrid::new(self, connectivity);
+        let mut visited = vec![false; grid.len()];
+
+        // Everything connected to the corner is outside
+        grid.flood_fill([0; N], &mut visited);
+
+        let mut pockets = Vec::new();
+
+        for voxel in grid.voxels() {
+            if grid.contains(voxel) || visited[grid.index(voxel)] {
+                continue;
+            }
+
+            let (filled, surface_area) = grid.flood_fill(voxel, &mut visited);
+            let mut cells: Vec<Droplet<N>> =
+                filled.into_iter().map(|air| grid.droplet(air)).collect();
+            cells.sort();
+
+            pockets.push(AirPocket {
+                bounds: Droplets(cells.clone()).bounds(),
+                cells,
+                surface_area,
+            });
+        }
+
+        pockets
+    }
+}
+
+impl Droplets<3> {
+    /// The faces of the droplets that touch air, split into exterior and interior faces.
+    fn surface_faces(&self, connectivity: Connectivity) -> (Vec<Face>, Vec<Face>) {
+        if self.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let grid = VoxelGrid::new(self, connectivity);
+        let mut visited = vec![false; grid.len()];
+
+        // Everything connected to the corner is outside
+        grid.flood_fill([0; 3], &mut visited);
+
+        let mut exterior = Vec::new();
+        let mut interior = Vec::new();
+
+        for droplet in self.iter() {
+            let voxel = grid.voxel(droplet);
+
+            for neighbor in grid.neighbors(voxel) {
+                if grid.contains(neighbor) {
+                    continue;
                 }
 
-                for x in ((x_min - 1)..=(x_max + 1)).rev() {
-                    let droplet = Droplet(x, y, z);
+                let axis = (0..3).find(|&axis| neighbor[axis] != voxel[axis]).unwrap();
+                let positive = neighbor[axis] > voxel[axis];
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
-                    }
+                let face = Face {
+                    droplet: *droplet,
+                    axis,
+                    positive,
+                };
+
+                if visited[grid.index(neighbor)] {
+                    exterior.push(face);
+                } else {
+                    interior.push(face);
                 }
             }
         }
 
-        // Check surfaces on y-axis
-        for x in x_min..=x_max {
-            for z in z_min..=z_max {
-                for y in (y_min - 1)..=(y_max + 1) {
-                    let droplet = Droplet(x, y, z);
+        (exterior, interior)
+    }
+}
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
-                    }
+/// A corner of a droplet.
+///
+/// The coordinates are wider than `Coord`, because droplets span to the next coordinate.
+type Vertex = (i64, i64, i64);
+
+/// A face of a droplet that touches air.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Face {
+    droplet: Droplet,
+
+    /// The axis that the face is perpendicular to, 0 to 2 for x to z.
+    axis: usize,
+
+    /// Whether the face points in the positive direction of the axis.
+    positive: bool,
+}
+
+impl Face {
+    /// The direction in which the face points, towards the air.
+    fn normal(&self) -> Vertex {
+        let mut normal = [0; 3];
+        normal[self.axis] = if self.positive { 1 } else { -1 };
+
+        (normal[0], normal[1], normal[2])
+    }
+
+    /// The corners of the face, counter-clockwise when seen from the air.
+    ///
+    /// The droplet at `x,y,z` spans from `x` to `x + 1` on the x-axis, and so on.
+    fn corners(&self) -> [Vertex; 4] {
+        let mut base = self.droplet.0.map(i64::from);
+
+        if self.positive {
+            base[self.axis] += 1;
+        }
+
+        // The other axes, in an order that forms a right-handed system with the face axis
+        let (first, second) = ((self.axis + 1) % 3, (self.axis + 2) % 3);
+
+        let corner = |first_offset: i64, second_offset: i64| {
+            let mut corner = base;
+            corner[first] += first_offset;
+            corner[second] += second_offset;
+
+            (corner[0], corner[1], corner[2])
+        };
+
+        let corners = [corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)];
+
+        if self.positive {
+            corners
+        } else {
+            [corners[0], corners[3], corners[2], corners[1]]
+        }
+    }
+}
+
+/// Write the named groups of faces as Wavefront OBJ mesh.
+///
+/// Every face becomes a quad and faces share their corners.
+fn write_obj(groups: &[(&str, &[Face])], mut writer: impl Write) -> io::Result<()> {
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut vertex_indices: HashMap<Vertex, usize> = HashMap::new();
+
+    // The indices of the corners of the faces, starting at 1
+    let group_faces: Vec<Vec<[usize; 4]>> = groups
+        .iter()
+        .map(|(_, faces)| {
+            faces
+                .iter()
+                .map(|face| {
+                    face.corners().map(|corner| {
+                        *vertex_indices.entry(corner).or_insert_with(|| {
+                            vertices.push(corner);
+                            vertices.len()
+                        })
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    writeln!(writer, "# Lava droplets")?;
+
+    for (x, y, z) in vertices {
+        writeln!(writer, "v {x} {y} {z}")?;
+    }
+
+    for ((name, _), faces) in groups.iter().zip(group_faces) {
+        writeln!(writer, "g {name}")?;
+
+        for [a, b, c, d] in faces {
+            writeln!(writer, "f {a} {b} {c} {d}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the named groups of faces as ASCII STL mesh, with one solid per group.
+///
+/// Every face becomes two triangles.
+fn write_stl(groups: &[(&str, &[Face])], mut writer: impl Write) -> io::Result<()> {
+    for (name, faces) in groups {
+        writeln!(writer, "solid {name}")?;
+
+        for face in faces.iter() {
+            let (nx, ny, nz) = face.normal();
+            let [a, b, c, d] = face.corners();
+
+            for triangle in [[a, b, c], [a, c, d]] {
+                writeln!(writer, "  facet normal {nx} {ny} {nz}")?;
+                writeln!(writer, "    outer loop")?;
+
+                for (x, y, z) in triangle {
+                    writeln!(writer, "      vertex {x} {y} {z}")?;
                 }
 
-                for y in ((y_min - 1)..=(y_max + 1)).rev() {
-                    let droplet = Droplet(x, y, z);
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+        }
+
+        writeln!(writer, "endsolid {name}")?;
+    }
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
-                    }
+    Ok(())
+}
+
+/// A connected pocket of air that is enclosed by droplets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AirPocket<const N: usize = 3> {
+    /// The positions of the air, sorted.
+    cells: Vec<Droplet<N>>,
+
+    /// The smallest and largest coordinates of the air on every axis.
+    bounds: (Droplet<N>, Droplet<N>),
+
+    /// The faces of droplets that touch the air.
+    surface_area: usize,
+}
+
+impl<const N: usize> AirPocket<N> {
+    fn volume(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+impl<const N: usize> Display for AirPocket<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = self.bounds;
+
+        write!(
+            f,
+            "Volume {:>4}, from {min} to {max}, interior surface area {}",
+            self.volume(),
+            self.surface_area
+        )
+    }
+}
+
+/// Droplets that can be added and removed one at a time, keeping the surface areas up to date.
+///
+/// Instead of the air outside, the enclosed air is tracked, because it is usually much smaller.
+#[derive(Debug, Clone)]
+struct IncrementalDroplets<const N: usize = 3> {
+    droplets: HashSet<Droplet<N>>,
+
+    /// The air that cannot reach the outside.
+    enclosed: HashSet<Droplet<N>>,
+
+    /// The number of droplets with each coordinate, on every axis.
+    ///
+    /// Their smallest and largest keys are the bounds of the droplets,
+    /// which shrink again when droplets are removed.
+    axis_coords: [BTreeMap<Coord, usize>; N],
+
+    /// The offsets to the cells that share a face.
+    faces: Vec<[isize; N]>,
+
+    /// The offsets to the cells that air can flow to.
+    connections: Vec<[isize; N]>,
+
+    surface_area: usize,
+
+    /// The faces of droplets that touch enclosed air.
+    interior_surface_area: usize,
+}
+
+impl<const N: usize> IncrementalDroplets<N> {
+    fn new(connectivity: Connectivity) -> Self {
+        Self {
+            droplets: HashSet::new(),
+            enclosed: HashSet::new(),
+            axis_coords: std::array::from_fn(|_| BTreeMap::new()),
+            faces: Connectivity::Face.offsets(),
+            connections: connectivity.offsets(),
+            surface_area: 0,
+            interior_surface_area: 0,
+        }
+    }
+
+    fn exterior_surface_area(&self) -> usize {
+        self.surface_area - self.interior_surface_area
+    }
+
+    /// Move the cell by all of the given offsets.
+    ///
+    /// Gives `None` for cells beyond the range of the coordinates, which are always outside.
+    fn offsets(
+        cell: Droplet<N>,
+        offsets: &[[isize; N]],
+    ) -> impl Iterator<Item = Option<Droplet<N>>> + '_ {
+        offsets.iter().map(move |offset| {
+            let mut coords = cell.0;
+
+            for (coord, delta) in coords.iter_mut().zip(offset) {
+                *coord = coord.checked_add(*delta as Coord)?;
+            }
+
+            Some(Droplet(coords))
+        })
+    }
+
+    /// The number of faces of the cell that touch droplets.
+    fn droplet_faces(&self, cell: Droplet<N>) -> usize {
+        Self::offsets(cell, &self.faces)
+            .flatten()
+            .filter(|neighbor| self.droplets.contains(neighbor))
+            .count()
+    }
+
+    /// The number of faces of the cell that touch enclosed air.
+    fn enclosed_faces(&self, cell: Droplet<N>) -> usize {
+        Self::offsets(cell, &self.faces)
+            .flatten()
+            .filter(|neighbor| self.enclosed.contains(neighbor))
+            .count()
+    }
+
+    /// Determine if the cell is outside of the bounds, where all air can reach the outside.
+    fn is_outside(&self, cell: Droplet<N>) -> bool {
+        self.axis_coords.iter().zip(cell.0).any(|(coords, coord)| {
+            match (coords.first_key_value(), coords.last_key_value()) {
+                (Some((&min, _)), Some((&max, _))) => coord < min || coord > max,
+                _ => true,
+            }
+        })
+    }
+
+    /// Determine if the cell is air that can reach the outside.
+    fn is_exterior(&self, cell: Droplet<N>) -> bool {
+        !self.droplets.contains(&cell) && !self.enclosed.contains(&cell)
+    }
+
+    /// Flood fill the air starting at the given cell.
+    ///
+    /// Returns the filled air if it is enclosed, or the air visited until the outside was reached.
+    fn fill_air(&self, start: Droplet<N>) -> Result<HashSet<Droplet<N>>, HashSet<Droplet<N>>> {
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+
+        while let Some(air) = stack.pop() {
+            if self.is_outside(air) {
+                return Err(visited);
+            }
+
+            for neighbor in Self::offsets(air, &self.connections) {
+                let Some(neighbor) = neighbor else {
+                    return Err(visited);
+                };
+
+                if !self.droplets.contains(&neighbor) && visited.insert(neighbor) {
+                    stack.push(neighbor);
                 }
             }
         }
 
-        // Check surfaces on x-axis
-        for x in x_min..=x_max {
-            for y in y_min..=y_max {
-                for z in (z_min - 1)..=(z_max + 1) {
-                    let droplet = Droplet(x, y, z);
+        Ok(visited)
+    }
+
+    /// Add a droplet, returns `false` if it was already there.
+    fn insert(&mut self, droplet: Droplet<N>) -> bool {
+        if self.droplets.contains(&droplet) {
+            return false;
+        }
+
+        let covered = self.droplet_faces(droplet);
+        let was_enclosed = self.enclosed.remove(&droplet);
+        self.droplets.insert(droplet);
+        self.surface_area = self.surface_area + 2 * N - 2 * covered;
+
+        for (coords, coord) in self.axis_coords.iter_mut().zip(droplet.0) {
+            *coords.entry(coord).or_default() += 1;
+        }
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
+        if was_enclosed {
+            // The droplet now covers the faces that touched the air in its place
+            self.interior_surface_area =
+                self.interior_surface_area + self.enclosed_faces(droplet) - covered;
+            return true;
+        }
+
+        // The droplet can cut off pockets of air from the outside
+        let mut exterior = HashSet::new();
+
+        for start in Self::offsets(droplet, &self.connections).flatten() {
+            if !self.is_exterior(start) || exterior.contains(&start) {
+                continue;
+            }
+
+            match self.fill_air(start) {
+                Ok(pocket) => {
+                    for air in pocket {
+                        self.interior_surface_area += self.droplet_faces(air);
+                        self.enclosed.insert(air);
                     }
                 }
+                Err(visited) => exterior.extend(visited),
+            }
+        }
 
-                for z in ((z_min - 1)..=(z_max + 1)).rev() {
-                    let droplet = Droplet(x, y, z);
+        true
+    }
 
-                    if self.binary_search(&droplet).is_ok() {
-                        surface_area += 1;
-                        break;
-                    }
+    /// Remove a droplet, returns `false` if it wasn't there.
+    fn remove(&mut self, droplet: Droplet<N>) -> bool {
+        if !self.droplets.remove(&droplet) {
+            return false;
+        }
+
+        for (coords, coord) in self.axis_coords.iter_mut().zip(droplet.0) {
+            if let Some(count) = coords.get_mut(&coord) {
+                *count -= 1;
+
+                if *count == 0 {
+                    coords.remove(&coord);
                 }
             }
         }
 
-        surface_area
+        let covered = self.droplet_faces(droplet);
+        self.surface_area = self.surface_area + 2 * covered - 2 * N;
+        self.interior_surface_area -= self.enclosed_faces(droplet);
+
+        let reaches_outside = Self::offsets(droplet, &self.connections)
+            .any(|neighbor| neighbor.is_none_or(|neighbor| self.is_exterior(neighbor)));
+
+        if !reaches_outside {
+            // The air in place of the droplet is enclosed as well
+            self.enclosed.insert(droplet);
+            self.interior_surface_area += covered;
+            return true;
+        }
+
+        // The pockets next to the droplet are opened to the outside
+        let mut stack = vec![droplet];
+
+        while let Some(air) = stack.pop() {
+            for neighbor in Self::offsets(air, &self.connections).flatten() {
+                if self.enclosed.remove(&neighbor) {
+                    self.interior_surface_area -= self.droplet_faces(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        true
     }
 }
 
-impl Deref for Droplets {
-    type Target = Vec<Droplet>;
+impl<const N: usize> Deref for Droplets<N> {
+    type Target = Vec<Droplet<N>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for Droplets {
+impl<const N: usize> DerefMut for Droplets<N> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl FromStr for Droplets {
+impl<const N: usize> FromStr for Droplets<N> {
     type Err = ParserError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let droplets: Result<Vec<Droplet>, ParserError> = s
+        let droplets: Result<Vec<Droplet<N>>, ParserError> = s
             .trim()
             .lines()
-            .map(|line| line.parse::<Droplet>())
+            .map(|line| line.parse::<Droplet<N>>())
             .collect();
 
         let mut droplets = droplets?;
@@ -201,25 +836,136 @@ impl Day for Day18 {
 
     fn run(&self) {
         let input = self.get_input();
+        let connectivity = option_value("--connectivity")
+            .map(|connectivity| {
+                connectivity
+                    .parse()
+                    .expect("Invalid connectivity, use face, edge or corner")
+            })
+            .unwrap_or_default();
+
+        // The number of coordinates on the first line determines the dimensions
+        match input
+            .lines()
+            .next()
+            .map_or(3, |line| line.split(',').count())
+        {
+            2 => run_in_dimensions::<2>(&input, connectivity),
+            3 => {
+                run_in_dimensions::<3>(&input, connectivity);
 
-        println!("Part 1: {}", part_1(&input));
-        println!("Part 2: {}", part_2(&input));
+                if let Some(path) = option_value("--mesh") {
+                    export_mesh(&input, &path, connectivity, has_flag("--interior"))
+                        .expect("Failed to write mesh");
+                }
+            }
+            4 => run_in_dimensions::<4>(&input, connectivity),
+            dimensions => panic!("Unsupported number of dimensions: {dimensions}"),
+        }
     }
 }
 
-fn part_1(input: &str) -> usize {
-    let droplets: Droplets = input.parse().unwrap();
+fn run_in_dimensions<const N: usize>(input: &str, connectivity: Connectivity) {
+    if has_flag("--replay") {
+        replay::<N>(input, connectivity);
+        return;
+    }
+
+    println!("Part 1: {}", part_1::<N>(input));
+    println!("Part 2: {}", part_2::<N>(input, connectivity));
+
+    if has_flag("--verbose") {
+        print_air_pockets::<N>(input, connectivity);
+    }
+}
+
+fn part_1<const N: usize>(input: &str) -> usize {
+    let droplets: Droplets<N> = input.parse().unwrap();
     droplets.surface_area()
 }
 
-fn part_2(input: &str) -> usize {
+fn part_2<const N: usize>(input: &str, connectivity: Connectivity) -> usize {
+    let droplets: Droplets<N> = input.parse().unwrap();
+    droplets.exterior_surface_area(connectivity)
+}
+
+/// Print the enclosed air pockets, which make the difference between both parts.
+fn print_air_pockets<const N: usize>(input: &str, connectivity: Connectivity) {
+    let droplets: Droplets<N> = input.parse().unwrap();
+    let pockets = droplets.air_pockets(connectivity);
+
+    println!();
+    println!("Air pockets: {}", pockets.len());
+
+    for pocket in pockets.iter() {
+        println!("{pocket}");
+    }
+
+    println!(
+        "Total volume: {}, total interior surface area: {}",
+        pockets.iter().map(AirPocket::volume).sum::<usize>(),
+        pockets
+            .iter()
+            .map(|pocket| pocket.surface_area)
+            .sum::<usize>()
+    );
+}
+
+/// Print the surface areas after every line of the input.
+///
+/// Lines starting with `remove ` remove the droplet again.
+fn replay<const N: usize>(input: &str, connectivity: Connectivity) {
+    let mut droplets = IncrementalDroplets::<N>::new(connectivity);
+
+    for line in input.trim().lines() {
+        let changed = match line.strip_prefix("remove ") {
+            Some(droplet) => droplets.remove(droplet.parse().expect("Invalid droplet")),
+            None => droplets.insert(line.parse().expect("Invalid droplet")),
+        };
+
+        println!(
+            "{line:<20} surface area {:>5}, exterior surface area {:>5}{}",
+            droplets.surface_area,
+            droplets.exterior_surface_area(),
+            if changed { "" } else { " (unchanged)" }
+        );
+    }
+}
+
+/// Write the surface of the droplets as mesh to the given file.
+///
+/// Files ending with `.stl` are written as ASCII STL, all others as Wavefront OBJ.
+/// The faces of air pockets are only included if `include_interior` is set.
+fn export_mesh(
+    input: &str,
+    path: &str,
+    connectivity: Connectivity,
+    include_interior: bool,
+) -> io::Result<()> {
     let droplets: Droplets = input.parse().unwrap();
-    droplets.exterior_surface_area()
+    let (exterior, interior) = droplets.surface_faces(connectivity);
+
+    let mut groups = vec![("exterior", exterior.as_slice())];
+    if include_interior {
+        groups.push(("interior", interior.as_slice()));
+    }
+
+    let writer = BufWriter::new(File::create(path)?);
+
+    if path.ends_with(".stl") {
+        write_stl(&groups, writer)?;
+    } else {
+        write_obj(&groups, writer)?;
+    }
+
+    println!("Wrote {path}");
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
 
     const EXAMPLE_INPUT: &'static str = "2,2,2
 1,2,2
@@ -236,16 +982,416 @@ mod tests {
 2,3,5
 ";
 
+    /// A cube of droplets with the given side length, without the given droplets.
+    fn cube_without(size: Coord, holes: &[Droplet]) -> Droplets {
+        let mut droplets = Vec::new();
+
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    let droplet = Droplet([x, y, z]);
+
+                    if !holes.contains(&droplet) {
+                        droplets.push(droplet);
+                    }
+                }
+            }
+        }
+
+        Droplets(droplets)
+    }
+
+    #[test]
+    fn should_exclude_enclosed_pocket() {
+        let droplets = cube_without(3, &[Droplet([1, 1, 1])]);
+
+        assert_eq!(droplets.surface_area(), 54 + 6);
+        assert_eq!(droplets.exterior_surface_area(Connectivity::Face), 54);
+    }
+
+    #[test]
+    fn should_include_faces_in_tunnel() {
+        // A straight tunnel through the middle of the cube
+        let droplets = cube_without(
+            3,
+            &[Droplet([1, 1, 0]), Droplet([1, 1, 1]), Droplet([1, 1, 2])],
+        );
+
+        assert_eq!(droplets.surface_area(), 54 - 2 + 12);
+        assert_eq!(
+            droplets.exterior_surface_area(Connectivity::Face),
+            54 - 2 + 12
+        );
+    }
+
+    #[test]
+    fn should_include_faces_in_bent_tunnel() {
+        // A tunnel that enters from the front, turns in the middle and leads to a pocket
+        let droplets = cube_without(
+            5,
+            &[
+                Droplet([2, 2, 0]),
+                Droplet([2, 2, 1]),
+                Droplet([2, 2, 2]),
+                Droplet([2, 3, 2]),
+                // Enclosed pocket next to the tunnel
+                Droplet([1, 1, 2]),
+            ],
+        );
+
+        assert_eq!(droplets.surface_area(), 150 - 1 + 3 * 4 + 4 + 1 + 6);
+        assert_eq!(
+            droplets.exterior_surface_area(Connectivity::Face),
+            150 - 1 + 3 * 4 + 4 + 1
+        );
+    }
+
+    #[test]
+    fn should_look_up_voxels() {
+        let droplets: Droplets = EXAMPLE_INPUT.parse().unwrap();
+        let grid = VoxelGrid::new(&droplets, Connectivity::Face);
+
+        assert_eq!(grid.size, [5, 5, 8]);
+        assert_eq!(grid.voxel(&Droplet([2, 2, 2])), [2, 2, 2]);
+        assert!(grid.contains([2, 2, 2]));
+        assert!(!grid.contains([2, 2, 5]));
+        assert_eq!(grid.neighbors([0, 0, 0]).count(), 3);
+        assert_eq!(grid.neighbors([2, 2, 2]).count(), 6);
+        assert_eq!(grid.neighbors([4, 4, 7]).count(), 3);
+
+        let grid = VoxelGrid::new(&droplets, Connectivity::Corner);
+        assert_eq!(grid.neighbors([2, 2, 2]).count(), 6);
+        assert_eq!(grid.connected([2, 2, 2]).count(), 26);
+        assert_eq!(grid.connected([0, 0, 0]).count(), 7);
+    }
+
+    #[rstest]
+    #[case(Connectivity::Face, 2, 4)]
+    #[case(Connectivity::Edge, 2, 8)]
+    #[case(Connectivity::Corner, 2, 8)]
+    #[case(Connectivity::Face, 3, 6)]
+    #[case(Connectivity::Edge, 3, 18)]
+    #[case(Connectivity::Corner, 3, 26)]
+    #[case(Connectivity::Edge, 4, 32)]
+    #[case(Connectivity::Corner, 4, 80)]
+    fn should_count_neighbor_offsets(
+        #[case] connectivity: Connectivity,
+        #[case] dimensions: usize,
+        #[case] expected: usize,
+    ) {
+        let count = match dimensions {
+            2 => connectivity.offsets::<2>().len(),
+            3 => connectivity.offsets::<3>().len(),
+            _ => connectivity.offsets::<4>().len(),
+        };
+
+        assert_eq!(count, expected);
+    }
+
+    #[test]
+    fn should_calculate_perimeter_in_2d() {
+        // A ring of squares around a hole, which only touches the outside at its corners
+        let input = "0,1\n1,0\n2,1\n1,2\n";
+
+        assert_eq!(part_1::<2>(input), 16);
+        assert_eq!(part_2::<2>(input, Connectivity::Face), 12);
+        assert_eq!(part_2::<2>(input, Connectivity::Corner), 16);
+
+        let droplets: Droplets<2> = input.parse().unwrap();
+        assert_eq!(
+            droplets.air_pockets(Connectivity::Face)[0].cells,
+            vec![Droplet([1, 1])]
+        );
+        assert!(droplets.air_pockets(Connectivity::Edge).is_empty());
+    }
+
+    #[test]
+    fn should_calculate_hyper_surface_in_4d() {
+        assert_eq!(part_1::<4>("0,0,0,0"), 8);
+        assert_eq!(part_1::<4>("0,0,0,0\n0,0,0,1"), 14);
+        assert_eq!(part_2::<4>("0,0,0,0\n0,0,0,1", Connectivity::Face), 14);
+    }
+
+    #[test]
+    fn should_let_air_through_diagonal_gaps() {
+        // The pocket at 2,2,5 shares edges with the air outside
+        assert_eq!(part_2::<3>(EXAMPLE_INPUT, Connectivity::Edge), 64);
+        assert_eq!(part_2::<3>(EXAMPLE_INPUT, Connectivity::Corner), 64);
+    }
+
+    #[test]
+    fn should_reject_wrong_dimensions() {
+        assert_eq!("1,2,3".parse::<Droplet<2>>(), Err(ParserError));
+        assert_eq!("1,2".parse::<Droplet<3>>(), Err(ParserError));
+        assert_eq!("1,2,3,4".parse(), Ok(Droplet([1, 2, 3, 4])));
+    }
+
+    #[test]
+    fn should_handle_coordinates_beyond_puzzle_range() {
+        let droplets = Droplets(vec![
+            Droplet([-130, 0, 0]),
+            Droplet([130, 0, 0]),
+            Droplet([0, 0, 130]),
+        ]);
+
+        assert_eq!(droplets.surface_area(), 18);
+        assert_eq!(droplets.exterior_surface_area(Connectivity::Face), 18);
+    }
+
+    #[test]
+    fn should_handle_widely_spread_coordinates() {
+        let droplets = Droplets(vec![
+            Droplet([-1_000_000_000, 0, 0]),
+            Droplet([1_000_000_000, 0, 0]),
+            Droplet([i32::MIN, i32::MAX, 0]),
+        ]);
+
+        assert_eq!(droplets.surface_area(), 18);
+        assert_eq!(droplets.exterior_surface_area(Connectivity::Corner), 18);
+
+        // A pocket far away from the other droplets
+        let mut droplets = cube_without(3, &[Droplet([1, 1, 1])]);
+        droplets.push(Droplet([1_000_000_000, 1_000_000_000, 1_000_000_000]));
+        droplets.sort();
+
+        let grid = VoxelGrid::new(&droplets, Connectivity::Face);
+        // The cube with its layer of air, the merged air and the far droplet with its air
+        assert_eq!(grid.size, [5 + 1 + 3; 3]);
+        assert_eq!(droplets.exterior_surface_area(Connectivity::Face), 54 + 6);
+        assert_eq!(
+            droplets.air_pockets(Connectivity::Face)[0].cells,
+            [Droplet([1, 1, 1])]
+        );
+    }
+
+    #[test]
+    fn should_find_air_pockets() {
+        let droplets: Droplets = EXAMPLE_INPUT.parse().unwrap();
+        let expected = AirPocket {
+            cells: vec![Droplet([2, 2, 5])],
+            bounds: (Droplet([2, 2, 5]), Droplet([2, 2, 5])),
+            surface_area: 6,
+        };
+
+        assert_eq!(droplets.air_pockets(Connectivity::Face), vec![expected]);
+
+        let droplets = cube_without(
+            5,
+            &[
+                Droplet([1, 1, 1]),
+                Droplet([1, 2, 1]),
+                Droplet([1, 3, 3]),
+                // Not enclosed
+                Droplet([0, 2, 2]),
+            ],
+        );
+        let pockets = droplets.air_pockets(Connectivity::Face);
+
+        assert_eq!(pockets.len(), 2);
+        assert_eq!(pockets[0].volume(), 2);
+        assert_eq!(pockets[0].bounds, (Droplet([1, 1, 1]), Droplet([1, 2, 1])));
+        assert_eq!(pockets[0].surface_area, 10);
+        assert_eq!(pockets[1].cells, vec![Droplet([1, 3, 3])]);
+        assert_eq!(
+            droplets.surface_area() - droplets.exterior_surface_area(Connectivity::Face),
+            pockets
+                .iter()
+                .map(|pocket| pocket.surface_area)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn should_split_surface_faces() {
+        let droplets: Droplets = EXAMPLE_INPUT.parse().unwrap();
+        let (exterior, interior) = droplets.surface_faces(Connectivity::Face);
+
+        assert_eq!(exterior.len(), 58);
+        assert_eq!(interior.len(), 6);
+
+        // Every edge is used in both directions by the faces next to it, so the mesh is closed
+        let mut edges: HashMap<(Vertex, Vertex), isize> = HashMap::new();
+        for face in exterior {
+            let corners = face.corners();
+
+            for idx in 0..4 {
+                let (from, to) = (corners[idx], corners[(idx + 1) % 4]);
+                *edges.entry((from.min(to), from.max(to))).or_default() +=
+                    if from < to { 1 } else { -1 };
+            }
+        }
+
+        assert!(edges.values().all(|&balance| balance == 0));
+    }
+
+    #[test]
+    fn should_write_obj() {
+        let droplets = Droplets(vec![Droplet([0, 0, 0])]);
+        let (exterior, _) = droplets.surface_faces(Connectivity::Face);
+
+        let mut output = Vec::new();
+        write_obj(&[("exterior", &exterior)], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            output.lines().filter(|line| line.starts_with("v ")).count(),
+            8
+        );
+        assert_eq!(
+            output.lines().filter(|line| line.starts_with("f ")).count(),
+            6
+        );
+        assert!(output.contains("g exterior\n"));
+        assert!(output.contains("v 1 1 1\n"));
+    }
+
+    #[test]
+    fn should_write_stl() {
+        let droplets = Droplets(vec![Droplet([0, 0, 0])]);
+        let (exterior, _) = droplets.surface_faces(Connectivity::Face);
+
+        let mut output = Vec::new();
+        write_stl(&[("exterior", &exterior[..1])], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let expected = "solid exterior
+  facet normal -1 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 0 1
+      vertex 0 1 1
+    endloop
+  endfacet
+  facet normal -1 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 1
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid exterior
+";
+
+        assert_eq!(output, expected);
+    }
+
+    /// Check the incremental surface areas against calculating them from scratch.
+    fn assert_areas_match<const N: usize>(
+        incremental: &IncrementalDroplets<N>,
+        connectivity: Connectivity,
+    ) {
+        let droplets = Droplets(incremental.droplets.iter().copied().collect());
+
+        assert_eq!(incremental.surface_area, droplets.surface_area());
+        assert_eq!(
+            incremental.exterior_surface_area(),
+            droplets.exterior_surface_area(connectivity)
+        );
+    }
+
+    #[rstest]
+    #[case(Connectivity::Face)]
+    #[case(Connectivity::Edge)]
+    #[case(Connectivity::Corner)]
+    fn should_update_surface_areas_incrementally(#[case] connectivity: Connectivity) {
+        let droplets: Droplets = EXAMPLE_INPUT.parse().unwrap();
+        let mut incremental = IncrementalDroplets::new(connectivity);
+
+        for droplet in droplets.iter() {
+            assert!(incremental.insert(*droplet));
+            assert_areas_match(&incremental, connectivity);
+        }
+
+        assert!(!incremental.insert(droplets[0]));
+
+        for droplet in droplets.iter().rev() {
+            assert!(incremental.remove(*droplet));
+            assert_areas_match(&incremental, connectivity);
+        }
+
+        assert!(!incremental.remove(droplets[0]));
+        assert_eq!(incremental.surface_area, 0);
+    }
+
+    #[test]
+    fn should_open_and_close_pockets_incrementally() {
+        let mut incremental = IncrementalDroplets::new(Connectivity::Face);
+
+        for droplet in cube_without(3, &[]).iter() {
+            incremental.insert(*droplet);
+        }
+
+        assert_eq!(incremental.exterior_surface_area(), 54);
+
+        // Hollow out the cube and then open it
+        incremental.remove(Droplet([1, 1, 1]));
+        assert_eq!(incremental.exterior_surface_area(), 54);
+        assert_eq!(incremental.interior_surface_area, 6);
+
+        incremental.remove(Droplet([1, 1, 0]));
+        assert_areas_match(&incremental, Connectivity::Face);
+        assert!(incremental.enclosed.is_empty());
+
+        // Close it again
+        incremental.insert(Droplet([1, 1, 0]));
+        assert_eq!(incremental.exterior_surface_area(), 54);
+        assert_eq!(incremental.interior_surface_area, 6);
+
+        // Droplets outside of the bounds extend them
+        incremental.insert(Droplet([5, 1, 1]));
+        assert_areas_match(&incremental, Connectivity::Face);
+
+        // And removing them shrinks the bounds again
+        incremental.remove(Droplet([5, 1, 1]));
+        assert!(incremental.is_outside(Droplet([3, 1, 1])));
+        assert!(!incremental.is_outside(Droplet([2, 1, 1])));
+    }
+
+    #[test]
+    fn should_shrink_bounds_when_removing_droplets() {
+        let mut incremental = IncrementalDroplets::new(Connectivity::Face);
+        incremental.insert(Droplet([-1_000_000_000, 0, 0]));
+        incremental.insert(Droplet([0, 0, 0]));
+        incremental.insert(Droplet([1_000_000_000, 0, 0]));
+        assert!(!incremental.is_outside(Droplet([-5, 0, 0])));
+
+        // The remaining droplets keep their coordinates within the bounds
+        incremental.remove(Droplet([-1_000_000_000, 0, 0]));
+        assert!(incremental.is_outside(Droplet([-5, 0, 0])));
+        assert!(!incremental.is_outside(Droplet([5, 0, 0])));
+
+        incremental.remove(Droplet([1_000_000_000, 0, 0]));
+        incremental.remove(Droplet([0, 0, 0]));
+        assert!(incremental.is_outside(Droplet([0, 0, 0])));
+    }
+
+    #[test]
+    fn should_handle_droplets_at_extreme_coordinates() {
+        let mut incremental = IncrementalDroplets::new(Connectivity::Face);
+
+        for x in [Coord::MIN, Coord::MAX] {
+            incremental.insert(Droplet([x, 0, 0]));
+            incremental.insert(Droplet([x, 2, 0]));
+        }
+        assert_eq!(incremental.surface_area, 4 * 6);
+
+        // The air between the droplets reaches beyond the range of the coordinates
+        assert_eq!(incremental.exterior_surface_area(), 4 * 6);
+
+        incremental.remove(Droplet([Coord::MAX, 2, 0]));
+        assert_eq!(incremental.exterior_surface_area(), 3 * 6);
+    }
+
     #[test]
     fn should_calculate_part_1_solution() {
-        let actual = part_1(EXAMPLE_INPUT);
+        let actual = part_1::<3>(EXAMPLE_INPUT);
 
         assert_eq!(actual, 64);
     }
 
     #[test]
     fn should_calculate_part_2_solution() {
-        let actual = part_2(EXAMPLE_INPUT);
+        let actual = part_2::<3>(EXAMPLE_INPUT, Connectivity::Face);
 
         assert_eq!(actual, 58);
     }
@@ -45,6 +45,8 @@ fn main() {
 
     let identifier = args
         .get(1)
+        // Options can be passed without a day identifier, to run the latest day
+        .filter(|arg| !arg.starts_with("--"))
         .map(|arg| arg.as_str())
         .unwrap_or_else(|| days.last().unwrap().identifier());
 
@@ -1,4 +1,4 @@
-use std::fs;
+use std::{env, fs};
 
 pub(crate) trait Day {
     /// The identifier of the day, e.g. "01" or "21".
@@ -16,3 +16,13 @@ pub(crate) trait Day {
         })
     }
 }
+
+/// Determine if the given flag, e.g. `--stats`, was passed on the command line.
+pub(crate) fn has_flag(flag: &str) -> bool {
+    env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Get the value following the given option on the command line, e.g. `--dot <dir>`.
+pub(crate) fn option_value(option: &str) -> Option<String> {
+    env::args().skip(1).skip_while(|arg| arg != option).nth(1)
+}